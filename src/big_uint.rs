@@ -0,0 +1,279 @@
+use std::{
+    cmp::Ordering,
+    fmt::Display,
+    ops::{Add, AddAssign, Mul},
+};
+
+/// Arbitrary-precision unsigned integer, for answers that outgrow the primitive integer types
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct BigUint {
+    /// Base 2^32 digits, least significant first, with no trailing zeros
+    limbs: Vec<u32>,
+}
+
+impl BigUint {
+    pub fn zero() -> Self {
+        Self::default()
+    }
+
+    pub fn one() -> Self {
+        Self::from(1u128)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    pub fn to_u128(&self) -> Option<u128> {
+        if self.limbs.len() > 4 {
+            return None;
+        }
+
+        Some(
+            self.limbs
+                .iter()
+                .rev()
+                .fold(0, |value, &limb| (value << 32) | limb as u128),
+        )
+    }
+
+    /// Number of bits needed to write the value, which is zero for zero
+    pub fn bit_len(&self) -> usize {
+        match self.limbs.last() {
+            Some(last) => self.limbs.len() * 32 - last.leading_zeros() as usize,
+            None => 0,
+        }
+    }
+
+    /// The bit worth 2^`index`
+    pub fn bit(&self, index: usize) -> bool {
+        self.limbs
+            .get(index / 32)
+            .is_some_and(|limb| (limb >> (index % 32)) & 1 == 1)
+    }
+
+    pub fn checked_sub(&self, other: &Self) -> Option<Self> {
+        if *self < *other {
+            return None;
+        }
+
+        let mut limbs = Vec::with_capacity(self.limbs.len());
+        let mut borrow = 0;
+        for (i, &limb) in self.limbs.iter().enumerate() {
+            let subtrahend = other.limbs.get(i).copied().unwrap_or(0) as i64 + borrow;
+            let mut difference = limb as i64 - subtrahend;
+            borrow = 0;
+            if difference < 0 {
+                difference += 1 << 32;
+                borrow = 1;
+            }
+            limbs.push(difference as u32);
+        }
+
+        Some(Self::normalized(limbs))
+    }
+
+    fn normalized(mut limbs: Vec<u32>) -> Self {
+        while limbs.last() == Some(&0) {
+            limbs.pop();
+        }
+
+        Self { limbs }
+    }
+
+    /// Divides in place by a small divisor, returning the remainder
+    fn div_rem_small(&mut self, divisor: u32) -> u32 {
+        let mut remainder = 0u64;
+        for limb in self.limbs.iter_mut().rev() {
+            let value = (remainder << 32) | *limb as u64;
+            *limb = (value / divisor as u64) as u32;
+            remainder = value % divisor as u64;
+        }
+
+        *self = Self::normalized(std::mem::take(&mut self.limbs));
+        remainder as u32
+    }
+}
+
+impl From<u128> for BigUint {
+    fn from(mut value: u128) -> Self {
+        let mut limbs = Vec::new();
+        while value > 0 {
+            limbs.push(value as u32);
+            value >>= 32;
+        }
+
+        Self { limbs }
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.limbs
+            .len()
+            .cmp(&other.limbs.len())
+            .then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl AddAssign<&BigUint> for BigUint {
+    fn add_assign(&mut self, other: &BigUint) {
+        if self.limbs.len() < other.limbs.len() {
+            self.limbs.resize(other.limbs.len(), 0);
+        }
+
+        let mut carry = 0u64;
+        for (i, limb) in self.limbs.iter_mut().enumerate() {
+            let sum = *limb as u64 + other.limbs.get(i).copied().unwrap_or(0) as u64 + carry;
+            *limb = sum as u32;
+            carry = sum >> 32;
+            if carry == 0 && i >= other.limbs.len() {
+                break;
+            }
+        }
+
+        if carry > 0 {
+            self.limbs.push(carry as u32);
+        }
+    }
+}
+
+impl Add<&BigUint> for &BigUint {
+    type Output = BigUint;
+
+    fn add(self, other: &BigUint) -> BigUint {
+        let mut sum = self.clone();
+        sum += other;
+        sum
+    }
+}
+
+impl Mul<&BigUint> for &BigUint {
+    type Output = BigUint;
+
+    fn mul(self, other: &BigUint) -> BigUint {
+        if self.is_zero() || other.is_zero() {
+            return BigUint::zero();
+        }
+
+        let mut limbs = vec![0u32; self.limbs.len() + other.limbs.len()];
+        for (i, &a) in self.limbs.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, &b) in other.limbs.iter().enumerate() {
+                let product = a as u64 * b as u64 + limbs[i + j] as u64 + carry;
+                limbs[i + j] = product as u32;
+                carry = product >> 32;
+            }
+            limbs[i + other.limbs.len()] = carry as u32;
+        }
+
+        BigUint::normalized(limbs)
+    }
+}
+
+impl Display for BigUint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(value) = self.to_u128() {
+            return write!(f, "{}", value);
+        }
+
+        // Peel off nine decimal digits at a time
+        let mut value = self.clone();
+        let mut chunks = Vec::new();
+        while !value.is_zero() {
+            chunks.push(value.div_rem_small(1_000_000_000));
+        }
+
+        write!(f, "{}", chunks.pop().unwrap_or(0))?;
+        for chunk in chunks.iter().rev() {
+            write!(f, "{:09}", chunk)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_carries_across_limbs() {
+        let mut sum = BigUint::from(u32::MAX as u128);
+        sum += &BigUint::one();
+        assert_eq!(sum.to_u128(), Some(1 << 32));
+
+        let sum = &BigUint::from(u128::MAX) + &BigUint::one();
+        assert_eq!(sum.to_u128(), None);
+        assert_eq!(sum.bit_len(), 129);
+        assert_eq!(sum.to_string(), "340282366920938463463374607431768211456");
+
+        // a shorter left-hand side still carries into the limbs it lacks
+        let mut sum = BigUint::one();
+        sum += &BigUint::from(u64::MAX as u128);
+        assert_eq!(sum.to_u128(), Some(1 << 64));
+    }
+
+    #[test]
+    fn mul_carries_across_limbs() {
+        let max = BigUint::from(u64::MAX as u128);
+        assert_eq!(
+            (&max * &max).to_u128(),
+            Some((u64::MAX as u128) * (u64::MAX as u128))
+        );
+
+        let max = BigUint::from(u128::MAX);
+        let square = &max * &max;
+        assert_eq!(
+            square.to_string(),
+            "115792089237316195423570985008687907852589419931798687112530834793049593217025"
+        );
+        assert!((&max * &BigUint::zero()).is_zero());
+    }
+
+    #[test]
+    fn checked_sub_borrows_across_limbs() {
+        let difference = BigUint::from(1 << 64).checked_sub(&BigUint::one());
+        assert_eq!(difference.and_then(|d| d.to_u128()), Some(u64::MAX as u128));
+
+        let big = &BigUint::from(u128::MAX) + &BigUint::one();
+        let difference = big.checked_sub(&BigUint::one()).expect("big is larger");
+        assert_eq!(difference.to_u128(), Some(u128::MAX));
+
+        assert!(BigUint::one().checked_sub(&big).is_none());
+        assert!(big.checked_sub(&big).is_some_and(|d| d.is_zero()));
+    }
+
+    #[test]
+    fn display_pads_chunks_below_a_billion() {
+        assert_eq!(BigUint::zero().to_string(), "0");
+
+        // 10^45 + 7 is too large for the u128 shortcut, and has chunks of all zeros and a chunk
+        // with leading zeros
+        let billion = BigUint::from(1_000_000_000u128);
+        let big = &(&(&billion * &billion) * &billion) * &(&billion * &billion);
+        let big = &big + &BigUint::from(7u128);
+        assert_eq!(big.to_u128(), None);
+        assert_eq!(big.to_string(), format!("1{}7", "0".repeat(44)));
+
+        let just_below = big
+            .checked_sub(&BigUint::from(8u128))
+            .expect("big is larger");
+        assert_eq!(just_below.to_string(), "9".repeat(45));
+    }
+
+    #[test]
+    fn bits_read_across_limbs() {
+        let value = BigUint::from((1 << 40) | 1);
+        assert_eq!(value.bit_len(), 41);
+        assert!(value.bit(0) && value.bit(40));
+        assert!(!value.bit(32) && !value.bit(100));
+        assert_eq!(BigUint::zero().bit_len(), 0);
+    }
+}
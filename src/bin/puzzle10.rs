@@ -1,6 +1,14 @@
-use std::{char, error::Error};
+use std::{
+    error::Error,
+    fmt::Display,
+    fs,
+    io::{Read, Seek, SeekFrom},
+    num::ParseIntError,
+    str::FromStr,
+};
 use utils::input_parser;
 
+#[derive(Clone)]
 struct Stack<T> {
     values: Vec<T>,
 }
@@ -26,94 +34,907 @@ impl<T> Stack<T> {
     }
 }
 
-fn syntax_points(syntax: char) -> u64 {
-    match syntax {
-        ')' => 3,
-        ']' => 57,
-        '}' => 1197,
-        '>' => 25137,
-        _ => panic!("Unknown syntax: {}", syntax),
+#[derive(Debug, Clone)]
+struct Delimiter {
+    pub open: String,
+    pub close: String,
+    pub syntax_points: u64,
+    pub completion_points: u64,
+}
+
+/// The bracket language being checked: which delimiters pair up, which regions are quoted (and
+/// so ignored), what each closing delimiter scores, and whether any other text is an error.
+#[derive(Debug, Clone)]
+struct DelimiterSet {
+    delimiters: Vec<Delimiter>,
+    quotes: Vec<(String, String)>,
+    strict: bool,
+}
+
+impl Default for DelimiterSet {
+    /// The four bracket kinds from the puzzle, with no quotes and no other syntax allowed
+    fn default() -> Self {
+        let mut delimiters = Self::new(true);
+        delimiters.add_pair("(", ")", 3, 1);
+        delimiters.add_pair("[", "]", 57, 2);
+        delimiters.add_pair("{", "}", 1197, 3);
+        delimiters.add_pair("<", ">", 25137, 4);
+        delimiters
     }
 }
 
-fn completion_points(syntax: char) -> u64 {
-    match syntax {
-        ')' => 1,
-        ']' => 2,
-        '}' => 3,
-        '>' => 4,
-        _ => panic!("Unknown syntax: {}", syntax),
+#[derive(Debug)]
+enum ParseDelimiterSetError {
+    UnknownDirective(String),
+    MissingField(usize),
+    ParseIntError(ParseIntError),
+}
+
+impl From<ParseIntError> for ParseDelimiterSetError {
+    fn from(e: ParseIntError) -> Self {
+        ParseDelimiterSetError::ParseIntError(e)
     }
 }
 
-fn closing(syntax: char) -> char {
-    match syntax {
-        '(' => ')',
-        '[' => ']',
-        '{' => '}',
-        '<' => '>',
-        _ => panic!("Unknown syntax: {}", syntax),
+impl Display for ParseDelimiterSetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseDelimiterSetError::UnknownDirective(directive) => {
+                write!(f, "Unknown directive '{}'", directive)
+            }
+            ParseDelimiterSetError::MissingField(line) => {
+                write!(f, "Missing field on line {}", line)
+            }
+            ParseDelimiterSetError::ParseIntError(e) => write!(f, "Failed to parse score: {}", e),
+        }
     }
 }
 
-fn part_1(input: &[String]) -> u64 {
-    let mut score: u64 = 0;
-    input.iter().for_each(|line| {
-        let mut syntax_stack = Stack::<char>::new();
-        for syntax in line.chars() {
-            match syntax {
-                '<' | '{' | '(' | '[' => syntax_stack.push(syntax),
-                '>' | '}' | ')' | ']' => {
-                    if syntax_stack.has_next() && closing(syntax_stack.pop().unwrap()) != syntax {
-                        score += syntax_points(syntax);
-                        break;
+impl Error for ParseDelimiterSetError {}
+
+impl FromStr for DelimiterSet {
+    type Err = ParseDelimiterSetError;
+
+    /// Parses one directive per line, ignoring blank lines and lines starting with `#`:
+    ///
+    /// ```text
+    /// pair <open> <close> <syntax points> <completion points>
+    /// quote <open> <close>
+    /// strict | lenient
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut delimiters = Self::new(true);
+        for (line_num, line) in s.lines().enumerate() {
+            let mut fields = line.split_whitespace();
+            let mut next = || fields.next().ok_or(Self::Err::MissingField(line_num + 1));
+            match line.split_whitespace().next() {
+                None => continue,
+                Some(comment) if comment.starts_with('#') => continue,
+                Some("pair") => {
+                    next()?;
+                    let (open, close) = (next()?, next()?);
+                    let (syntax_points, completion_points) = (next()?.parse()?, next()?.parse()?);
+                    delimiters.add_pair(open, close, syntax_points, completion_points);
+                }
+                Some("quote") => {
+                    next()?;
+                    let (open, close) = (next()?, next()?);
+                    delimiters.add_quote(open, close);
+                }
+                Some("strict") => delimiters.strict = true,
+                Some("lenient") => delimiters.strict = false,
+                Some(directive) => return Err(Self::Err::UnknownDirective(directive.to_owned())),
+            }
+        }
+
+        Ok(delimiters)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenKind {
+    Open(usize),
+    Close(usize),
+    Quoted,
+    /// A quote that is still open at the end of the line, by index into the set's quotes
+    Unterminated(usize),
+    Other,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Lexeme {
+    Quote(usize),
+    Open(usize),
+    Close(usize),
+    Other,
+}
+
+struct Token<'a> {
+    kind: TokenKind,
+    /// 1-based column of the first character of the token
+    column: usize,
+    text: &'a str,
+}
+
+impl DelimiterSet {
+    /// An empty set. When `strict`, anything that is not a delimiter or quoted is an error.
+    pub fn new(strict: bool) -> Self {
+        Self {
+            delimiters: Vec::new(),
+            quotes: Vec::new(),
+            strict,
+        }
+    }
+
+    /// A small set for C-like code, with block comments as a multi-character delimiter
+    pub fn code() -> Self {
+        let mut code = Self::new(false);
+        code.add_pair("(", ")", 3, 1);
+        code.add_pair("{", "}", 1197, 3);
+        code.add_pair("/*", "*/", 5, 5);
+        code.add_quote("\"", "\"");
+        code
+    }
+
+    pub fn add_pair(
+        &mut self,
+        open: &str,
+        close: &str,
+        syntax_points: u64,
+        completion_points: u64,
+    ) {
+        self.delimiters.push(Delimiter {
+            open: open.to_owned(),
+            close: close.to_owned(),
+            syntax_points,
+            completion_points,
+        });
+    }
+
+    pub fn add_quote(&mut self, open: &str, close: &str) {
+        self.quotes.push((open.to_owned(), close.to_owned()));
+    }
+
+    fn closing(&self, close: &str) -> Option<&Delimiter> {
+        self.delimiters
+            .iter()
+            .find(|delimiter| delimiter.close == close)
+    }
+
+    pub fn syntax_points(&self, close: &str) -> u64 {
+        self.closing(close)
+            .map_or(0, |delimiter| delimiter.syntax_points)
+    }
+
+    pub fn completion_points(&self, close: &str) -> u64 {
+        self.closing(close)
+            .map_or(0, |delimiter| delimiter.completion_points)
+    }
+
+    /// Finds the longest quote or delimiter at the start of `rest`, returning it with its length
+    /// in bytes. Returns `None` if more input could still turn `rest` into a longer match, which
+    /// can only happen when it is not `at_end` of the line.
+    fn lex(&self, rest: &[u8], at_end: bool) -> Option<(Lexeme, usize)> {
+        let patterns = self
+            .quotes
+            .iter()
+            .enumerate()
+            .map(|(index, (open, _))| (open, Lexeme::Quote(index)))
+            .chain(
+                self.delimiters
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(index, delimiter)| {
+                        [
+                            (&delimiter.open, Lexeme::Open(index)),
+                            (&delimiter.close, Lexeme::Close(index)),
+                        ]
+                    }),
+            );
+
+        let char_len = match rest[0] {
+            byte if byte < 0x80 => 1,
+            byte if byte >= 0xF0 => 4,
+            byte if byte >= 0xE0 => 3,
+            _ => 2,
+        };
+        let mut longest = (Lexeme::Other, char_len);
+        let mut matched = false;
+        for (pattern, lexeme) in patterns {
+            let pattern = pattern.as_bytes();
+            if !at_end && pattern.len() > rest.len() && pattern.starts_with(rest) {
+                return None;
+            }
+
+            if rest.starts_with(pattern) && (!matched || pattern.len() > longest.1) {
+                longest = (lexeme, pattern.len());
+                matched = true;
+            }
+        }
+
+        if !matched && !at_end && char_len > rest.len() {
+            return None;
+        }
+
+        Some(longest)
+    }
+
+    /// Splits a line into tokens, always taking the longest delimiter or quote that matches.
+    fn tokenize<'a>(&self, line: &'a str) -> Vec<Token<'a>> {
+        let mut tokens = Vec::new();
+        let mut i = 0;
+        let mut column = 1;
+        while i < line.len() {
+            let rest = &line[i..];
+            let (lexeme, len) = self
+                .lex(rest.as_bytes(), true)
+                .expect("Lexing at the end of a line always matches");
+            let (kind, len) = match lexeme {
+                Lexeme::Quote(index) => {
+                    let close = self.quotes[index].1.as_str();
+                    match rest[len..].find(close) {
+                        Some(end) => (TokenKind::Quoted, len + end + close.len()),
+                        None => (TokenKind::Unterminated(index), rest.len()),
                     }
                 }
-                _ => panic!("Unknown syntax: {}", syntax),
+                Lexeme::Open(index) => (TokenKind::Open(index), len),
+                Lexeme::Close(index) => (TokenKind::Close(index), len),
+                Lexeme::Other => (TokenKind::Other, len),
+            };
+
+            let text = &rest[..len];
+            tokens.push(Token { kind, column, text });
+            column += text.chars().count();
+            i += len;
+        }
+
+        tokens
+    }
+
+    pub fn check(&self, line: &str) -> Diagnostic {
+        self.check_with(line, self.strict)
+    }
+
+    fn check_with(&self, line: &str, strict: bool) -> Diagnostic {
+        let mut syntax_stack = Stack::<usize>::new();
+        for token in self.tokenize(line) {
+            let column = token.column;
+            let found = token.text.to_owned();
+            match token.kind {
+                TokenKind::Open(index) => syntax_stack.push(index),
+                TokenKind::Close(index) => match syntax_stack.pop() {
+                    Some(open) if open != index => {
+                        return Diagnostic::Corrupted {
+                            column,
+                            expected: self.delimiters[open].close.clone(),
+                            found,
+                        }
+                    }
+                    Some(_) => {}
+                    None => return Diagnostic::UnexpectedClose { column, found },
+                },
+                TokenKind::Quoted => {}
+                TokenKind::Unterminated(quote) => {
+                    return Diagnostic::UnterminatedQuote {
+                        column,
+                        expected: self.quotes[quote].1.clone(),
+                    }
+                }
+                TokenKind::Other => {
+                    if strict {
+                        return Diagnostic::UnknownSyntax { column, found };
+                    }
+                }
+            }
+        }
+
+        if !syntax_stack.has_next() {
+            return Diagnostic::Valid;
+        }
+
+        let mut missing = Vec::new();
+        while let Some(open) = syntax_stack.pop() {
+            missing.push(self.delimiters[open].close.clone());
+        }
+
+        Diagnostic::Incomplete { missing }
+    }
+
+    /// Produces a balanced version of `line` using as few delimiter insertions and deletions as
+    /// possible, returning the corrected line and the number of edits. Unclosed delimiters are
+    /// closed where their enclosed span ends, stray closing delimiters are deleted, unterminated
+    /// quotes are closed at the end of the line, and anything else is left alone.
+    ///
+    /// Edits are chosen token by token, but deleting or inserting a token can join the characters
+    /// either side of it into a new delimiter. When the corrected line no longer tokenizes the
+    /// same way, the first token that changed is deleted as well and the edits are chosen again,
+    /// so a few more edits than the minimum may be needed.
+    pub fn fix(&self, line: &str) -> (String, usize) {
+        let tokens = self.tokenize(line);
+        let mut deleted = vec![false; tokens.len()];
+        loop {
+            let (pieces, edits) = self.fix_tokens(&tokens, &deleted);
+            let fixed: String = pieces.iter().map(|(text, _)| text.as_str()).collect();
+            if self.check_with(&fixed, false) == Diagnostic::Valid {
+                return (fixed, edits);
+            }
+
+            let retokenized = self.tokenize(&fixed);
+            let changed = pieces
+                .iter()
+                .zip(retokenized.iter())
+                .position(|((text, _), token)| text != token.text)
+                .unwrap_or(pieces.len().min(retokenized.len()));
+            // Inserted closing delimiters always belong to a kept opening one, so some piece
+            // came from the line
+            let culprit = pieces[changed..]
+                .iter()
+                .chain(pieces[..changed].iter().rev())
+                .find_map(|&(_, token)| token)
+                .expect("A kept token caused the change");
+            deleted[culprit] = true;
+        }
+    }
+
+    /// Chooses the fewest edits to balance `tokens` without using any `deleted` ones, returning
+    /// the pieces of text that make up the corrected line, along with the index of the token
+    /// each piece came from if it was not inserted
+    fn fix_tokens(
+        &self,
+        tokens: &[Token],
+        deleted: &[bool],
+    ) -> (Vec<(String, Option<usize>)>, usize) {
+        let len = tokens.len();
+
+        // costs[i][j] is the fewest edits needed to balance tokens[i..j]
+        let mut costs = vec![vec![0; len + 1]; len + 1];
+        for start in (0..len).rev() {
+            for end in start + 1..=len {
+                costs[start][end] = match tokens[start].kind {
+                    _ if deleted[start] => costs[start + 1][end] + 1,
+                    TokenKind::Open(index) => {
+                        let mut cost = costs[start + 1][end] + 1;
+                        for close in start + 1..end {
+                            if tokens[close].kind == TokenKind::Close(index) && !deleted[close] {
+                                cost = cost.min(costs[start + 1][close] + costs[close + 1][end]);
+                            }
+                        }
+                        cost
+                    }
+                    TokenKind::Close(_) | TokenKind::Unterminated(_) => costs[start + 1][end] + 1,
+                    TokenKind::Quoted | TokenKind::Other => costs[start + 1][end],
+                };
+            }
+        }
+
+        fn rebuild(
+            delimiters: &DelimiterSet,
+            tokens: &[Token],
+            deleted: &[bool],
+            costs: &[Vec<usize>],
+            start: usize,
+            end: usize,
+            pieces: &mut Vec<(String, Option<usize>)>,
+        ) {
+            if start == end {
+                return;
+            }
+
+            let token = &tokens[start];
+            match token.kind {
+                _ if deleted[start] => {}
+                TokenKind::Open(index) => {
+                    for close in start + 1..end {
+                        if tokens[close].kind == TokenKind::Close(index)
+                            && !deleted[close]
+                            && costs[start][end] == costs[start + 1][close] + costs[close + 1][end]
+                        {
+                            pieces.push((token.text.to_owned(), Some(start)));
+                            rebuild(delimiters, tokens, deleted, costs, start + 1, close, pieces);
+                            pieces.push((tokens[close].text.to_owned(), Some(close)));
+                            rebuild(delimiters, tokens, deleted, costs, close + 1, end, pieces);
+                            return;
+                        }
+                    }
+
+                    pieces.push((token.text.to_owned(), Some(start)));
+                    rebuild(delimiters, tokens, deleted, costs, start + 1, end, pieces);
+                    pieces.push((delimiters.delimiters[index].close.clone(), None));
+                    return;
+                }
+                TokenKind::Close(_) => {}
+                TokenKind::Unterminated(quote) => {
+                    let closed = token.text.to_owned() + &delimiters.quotes[quote].1;
+                    pieces.push((closed, Some(start)));
+                }
+                TokenKind::Quoted | TokenKind::Other => {
+                    pieces.push((token.text.to_owned(), Some(start)))
+                }
+            }
+
+            rebuild(delimiters, tokens, deleted, costs, start + 1, end, pieces);
+        }
+
+        let mut pieces = Vec::new();
+        rebuild(self, tokens, deleted, &costs, 0, len, &mut pieces);
+        (pieces, costs[0][len])
+    }
+}
+
+/// Result of checking a single line. Columns are 1-based.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Diagnostic {
+    Valid,
+    Corrupted {
+        column: usize,
+        expected: String,
+        found: String,
+    },
+    Incomplete {
+        missing: Vec<String>,
+    },
+    UnexpectedClose {
+        column: usize,
+        found: String,
+    },
+    UnknownSyntax {
+        column: usize,
+        found: String,
+    },
+    UnterminatedQuote {
+        column: usize,
+        expected: String,
+    },
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Diagnostic::Valid => write!(f, "valid"),
+            Diagnostic::Corrupted {
+                column,
+                expected,
+                found,
+            } => write!(
+                f,
+                "corrupted at column {}: expected '{}', found '{}'",
+                column, expected, found
+            ),
+            Diagnostic::Incomplete { missing } => {
+                write!(f, "incomplete: missing '{}'", missing.concat())
             }
+            Diagnostic::UnexpectedClose { column, found } => write!(
+                f,
+                "unexpected '{}' at column {} with nothing left to close",
+                found, column
+            ),
+            Diagnostic::UnknownSyntax { column, found } => {
+                write!(f, "unknown syntax '{}' at column {}", found, column)
+            }
+            Diagnostic::UnterminatedQuote { column, expected } => write!(
+                f,
+                "quote opened at column {} is never closed with '{}'",
+                column, expected
+            ),
         }
-    });
+    }
+}
 
-    score
+impl Diagnostic {
+    /// The closing delimiter that made the line corrupted, if there is one
+    pub fn illegal_close(&self) -> Option<&str> {
+        match self {
+            Diagnostic::Corrupted { found, .. } | Diagnostic::UnexpectedClose { found, .. } => {
+                Some(found)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        !matches!(self, Diagnostic::Valid | Diagnostic::Incomplete { .. })
+    }
+}
+
+/// Number of characters in some UTF-8 bytes
+fn char_count(bytes: &[u8]) -> usize {
+    bytes.iter().filter(|&&byte| byte & 0xC0 != 0x80).count()
+}
+
+/// Checkpoint of a [`Validator`], which can be restored later to resume from the same point
+#[derive(Clone)]
+struct ValidatorState {
+    syntax_stack: Stack<usize>,
+    /// Bytes of the current line that cannot be tokenized until more input arrives
+    pending: Vec<u8>,
+    /// Quote currently being skipped, and the column it was opened at
+    quote: Option<(usize, usize)>,
+    line: usize,
+    column: usize,
+    /// Set once the current line has reported an error, so the rest of it can be skipped
+    failed: bool,
 }
 
-fn part_2(input: &[String]) -> u64 {
-    let mut scores = Vec::new();
+/// Checks input that arrives in chunks of bytes, holding only the open delimiters and at most one
+/// partial token in memory. Lines are checked independently, as with [`DelimiterSet::check`].
+struct Validator<'a> {
+    delimiters: &'a DelimiterSet,
+    state: ValidatorState,
+}
+
+impl<'a> Validator<'a> {
+    pub fn new(delimiters: &'a DelimiterSet) -> Self {
+        Self {
+            delimiters,
+            state: ValidatorState {
+                syntax_stack: Stack::new(),
+                pending: Vec::new(),
+                quote: None,
+                line: 1,
+                column: 1,
+                failed: false,
+            },
+        }
+    }
+
+    pub fn snapshot(&self) -> ValidatorState {
+        self.state.clone()
+    }
+
+    pub fn restore(&mut self, state: ValidatorState) {
+        self.state = state;
+    }
+
+    /// Feeds the next chunk of input, returning the 1-based line number and diagnostic of every
+    /// problem found so far. Errors are reported as soon as the offending token is complete, and
+    /// incomplete lines as soon as their newline is seen.
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<(usize, Diagnostic)> {
+        let mut diagnostics = Vec::new();
+        let mut pending = std::mem::take(&mut self.state.pending);
+        pending.extend_from_slice(chunk);
+
+        let mut consumed = 0;
+        loop {
+            let rest = &pending[consumed..];
+            let line_end = rest.iter().position(|&byte| byte == b'\n');
+            let line = &rest[..line_end.unwrap_or(rest.len())];
+            let used = self.advance(line, line_end.is_some(), &mut diagnostics);
+            consumed += used;
+            if line_end != Some(used) {
+                break;
+            }
+
+            self.end_line(&mut diagnostics);
+            consumed += 1;
+        }
+
+        pending.drain(..consumed);
+        self.state.pending = pending;
+        diagnostics
+    }
+
+    /// Signals the end of the input, reporting the final line if it had no trailing newline.
+    pub fn finish(mut self) -> Vec<(usize, Diagnostic)> {
+        let mut diagnostics = Vec::new();
+        let pending = std::mem::take(&mut self.state.pending);
+        self.advance(&pending, true, &mut diagnostics);
+        if self.state.column > 1 {
+            self.end_line(&mut diagnostics);
+        }
 
-    input.iter().for_each(|line| {
-        let mut syntax_stack = Stack::<char>::new();
-        let mut line_is_valid = true;
-        for syntax in line.chars() {
-            match syntax {
-                '<' | '{' | '(' | '[' => syntax_stack.push(syntax),
-                '>' | '}' | ')' | ']' => {
-                    if syntax_stack.has_next() && closing(syntax_stack.pop().unwrap()) != syntax {
-                        line_is_valid = false;
-                        break;
+        diagnostics
+    }
+
+    /// Consumes as much of a (possibly partial) line as can be tokenized, returning the number of
+    /// bytes used.
+    fn advance(
+        &mut self,
+        line: &[u8],
+        at_end: bool,
+        diagnostics: &mut Vec<(usize, Diagnostic)>,
+    ) -> usize {
+        let state = &mut self.state;
+        let mut i = 0;
+        while i < line.len() {
+            if state.failed {
+                state.column += char_count(&line[i..]);
+                return line.len();
+            }
+
+            let rest = &line[i..];
+            if let Some((quote, _)) = state.quote {
+                let close = self.delimiters.quotes[quote].1.as_bytes();
+                match rest.windows(close.len()).position(|window| window == close) {
+                    Some(end) => {
+                        state.column += char_count(&rest[..end + close.len()]);
+                        state.quote = None;
+                        i += end + close.len();
+                    }
+                    None => {
+                        // Hold back anything that could be the start of the closing quote
+                        let keep = if at_end {
+                            0
+                        } else {
+                            (close.len() - 1).min(rest.len())
+                        };
+                        state.column += char_count(&rest[..rest.len() - keep]);
+                        return line.len() - keep;
                     }
                 }
-                _ => panic!("Unknown syntax: {}", syntax),
+
+                continue;
             }
+
+            let (lexeme, len) = match self.delimiters.lex(rest, at_end) {
+                Some(lexeme) => lexeme,
+                None => return i,
+            };
+
+            let column = state.column;
+            let found = || String::from_utf8_lossy(&rest[..len]).into_owned();
+            let error = match lexeme {
+                Lexeme::Quote(quote) => {
+                    state.quote = Some((quote, column));
+                    None
+                }
+                Lexeme::Open(index) => {
+                    state.syntax_stack.push(index);
+                    None
+                }
+                Lexeme::Close(index) => match state.syntax_stack.pop() {
+                    Some(open) if open != index => Some(Diagnostic::Corrupted {
+                        column,
+                        expected: self.delimiters.delimiters[open].close.clone(),
+                        found: found(),
+                    }),
+                    Some(_) => None,
+                    None => Some(Diagnostic::UnexpectedClose {
+                        column,
+                        found: found(),
+                    }),
+                },
+                Lexeme::Other if self.delimiters.strict => Some(Diagnostic::UnknownSyntax {
+                    column,
+                    found: found(),
+                }),
+                Lexeme::Other => None,
+            };
+
+            if let Some(error) = error {
+                diagnostics.push((state.line, error));
+                state.failed = true;
+            }
+
+            state.column += char_count(&rest[..len]);
+            i += len;
         }
 
-        if line_is_valid {
-            let mut score = 0;
-            while syntax_stack.has_next() {
-                score = score * 5 + completion_points(closing(syntax_stack.pop().unwrap()));
+        line.len()
+    }
+
+    fn end_line(&mut self, diagnostics: &mut Vec<(usize, Diagnostic)>) {
+        let state = &mut self.state;
+        if let Some((quote, column)) = state.quote {
+            diagnostics.push((
+                state.line,
+                Diagnostic::UnterminatedQuote {
+                    column,
+                    expected: self.delimiters.quotes[quote].1.clone(),
+                },
+            ));
+        } else if !state.failed && state.syntax_stack.has_next() {
+            let mut missing = Vec::new();
+            while let Some(open) = state.syntax_stack.pop() {
+                missing.push(self.delimiters.delimiters[open].close.clone());
             }
 
-            scores.push(score);
+            diagnostics.push((state.line, Diagnostic::Incomplete { missing }));
         }
-    });
+
+        state.syntax_stack = Stack::new();
+        state.quote = None;
+        state.failed = false;
+        state.line += 1;
+        state.column = 1;
+    }
+}
+
+fn part_1(delimiters: &DelimiterSet, diagnostics: &[Diagnostic]) -> u64 {
+    diagnostics
+        .iter()
+        .filter_map(|diagnostic| diagnostic.illegal_close())
+        .map(|close| delimiters.syntax_points(close))
+        .sum()
+}
+
+/// Middle completion score of the incomplete lines, or `None` if no line is incomplete
+fn part_2(delimiters: &DelimiterSet, diagnostics: &[Diagnostic]) -> Option<u64> {
+    let mut scores: Vec<u64> = diagnostics
+        .iter()
+        .filter_map(|diagnostic| match diagnostic {
+            Diagnostic::Incomplete { missing } => Some(missing.iter().fold(0, |score, close| {
+                score * 5 + delimiters.completion_points(close)
+            })),
+            _ => None,
+        })
+        .collect();
 
     scores.sort_unstable();
 
-    scores[scores.len() / 2]
+    scores.get(scores.len() / 2).copied()
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
+    // An optional path to a delimiter set file replaces the puzzle's brackets
+    let delimiters = match std::env::args().nth(1) {
+        Some(path) => fs::read_to_string(path)?.parse()?,
+        None => DelimiterSet::default(),
+    };
+
     let input = input_parser::parse("puzzle10");
-    println!("Part 1: {}", part_1(&input));
-    println!("Part 2: {}", part_2(&input));
+    let diagnostics: Vec<Diagnostic> = input.iter().map(|line| delimiters.check(line)).collect();
+    println!("Part 1: {}", part_1(&delimiters, &diagnostics));
+    match part_2(&delimiters, &diagnostics) {
+        Some(score) => println!("Part 2: {}", score),
+        None => println!("Part 2: no line is incomplete"),
+    }
+
+    for (line_num, (line, diagnostic)) in input.iter().zip(diagnostics.iter()).enumerate() {
+        if diagnostic.is_error() {
+            let (fixed, edits) = delimiters.fix(line);
+            println!("Line {}: {}", line_num + 1, diagnostic);
+            println!("  fixed with {} edit(s): {}", edits, fixed);
+            break;
+        }
+    }
+
+    // Validate the input again a fixed-size buffer at a time, as if it were a huge file. The
+    // run stops after the first buffer and is resumed from a checkpoint with the file reopened.
+    let mut buffer = [0; 4096];
+    let mut validator = Validator::new(&delimiters);
+    let read = input_parser::open("puzzle10").read(&mut buffer)?;
+    let mut streamed = validator.feed(&buffer[..read]);
+    let checkpoint = (validator.snapshot(), read as u64);
+
+    let mut validator = Validator::new(&delimiters);
+    validator.restore(checkpoint.0);
+    let mut file = input_parser::open("puzzle10");
+    file.seek(SeekFrom::Start(checkpoint.1))?;
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        streamed.append(&mut validator.feed(&buffer[..read]));
+    }
+    streamed.append(&mut validator.finish());
+
+    let streamed: Vec<Diagnostic> = streamed
+        .into_iter()
+        .map(|(_, diagnostic)| diagnostic)
+        .collect();
+    println!("Part 1 (streamed): {}", part_1(&delimiters, &streamed));
+    match part_2(&delimiters, &streamed) {
+        Some(score) => println!("Part 2 (streamed): {}", score),
+        None => println!("Part 2 (streamed): no line is incomplete"),
+    }
+
+    let code = DelimiterSet::code();
+    let sample = r#"/* f(")") */ fn main() { println!("{"); "#;
+    println!("Sample '{}': {}", sample, code.check(sample));
+    println!("  fixed: {}", code.fix(sample).0);
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code() -> DelimiterSet {
+        let mut code = DelimiterSet::code();
+        code.add_quote("'", "'");
+        code
+    }
+
+    const EXAMPLE: &str = "[({(<(())[]>[[{[]{<()<>>
+[(()[<>])]({[<{<<[]>>(
+{([(<{}[<>[]}>{[]{[(<()>
+(((({<>}<{<{<>}{[]{[]{}
+[[<[([]))<([[{}[[()]]]
+[{[{({}]{}}([{[{{{}}([]
+{<[[]]>}<{[{[{[]{()[[[]
+[<(<(<(<{}))><([]([]()
+<{([([[(<>()){}]>(<<{{
+<{([{{}}[<[[[<>{}]]]>[]]
+";
+
+    fn validate(validator: &mut Validator, chunks: &[&[u8]]) -> Vec<(usize, Diagnostic)> {
+        chunks
+            .iter()
+            .flat_map(|chunk| validator.feed(chunk))
+            .collect()
+    }
+
+    #[test]
+    fn middle_score_needs_an_incomplete_line() {
+        let delimiters = DelimiterSet::default();
+        let diagnostics: Vec<Diagnostic> =
+            EXAMPLE.lines().map(|line| delimiters.check(line)).collect();
+        assert_eq!(part_1(&delimiters, &diagnostics), 26397);
+        assert_eq!(part_2(&delimiters, &diagnostics), Some(288957));
+
+        let complete: Vec<Diagnostic> = diagnostics
+            .into_iter()
+            .filter(|diagnostic| !matches!(diagnostic, Diagnostic::Incomplete { .. }))
+            .collect();
+        assert_eq!(part_2(&delimiters, &complete), None);
+        assert_eq!(part_2(&delimiters, &[]), None);
+    }
+
+    #[test]
+    fn restoring_a_snapshot_resumes_validation() {
+        let delimiters = DelimiterSet::default();
+        let chunks: Vec<&[u8]> = EXAMPLE.as_bytes().chunks(7).collect();
+        let middle = chunks.len() / 2;
+
+        let mut validator = Validator::new(&delimiters);
+        let mut expected = validate(&mut validator, &chunks[..middle]);
+        let state = validator.snapshot();
+        let reported = expected.len();
+        expected.append(&mut validate(&mut validator, &chunks[middle..]));
+        expected.append(&mut validator.finish());
+
+        let mut resumed = Validator::new(&delimiters);
+        resumed.restore(state);
+        let mut diagnostics = expected[..reported].to_vec();
+        diagnostics.append(&mut validate(&mut resumed, &chunks[middle..]));
+        diagnostics.append(&mut resumed.finish());
+        assert_eq!(diagnostics, expected);
+        assert_eq!(diagnostics.len(), 10);
+    }
+
+    #[test]
+    fn chunk_boundaries_do_not_change_diagnostics() {
+        let code = code();
+        let sample = r#"/* f(")") */ fn main() { println!("{"); "#;
+        for size in 1..=sample.len() {
+            let chunks: Vec<&[u8]> = sample.as_bytes().chunks(size).collect();
+            let mut validator = Validator::new(&code);
+            let mut streamed = validate(&mut validator, &chunks);
+            streamed.append(&mut validator.finish());
+            assert_eq!(
+                streamed,
+                vec![(1, code.check(sample))],
+                "chunks of {}",
+                size
+            );
+        }
+    }
+
+    #[test]
+    fn fixed_lines_check_as_valid() {
+        let code = code();
+        for line in ["{///)*", "(aé*}/", "é*a''*)/}}"] {
+            assert_eq!(code.check(&code.fix(line).0), Diagnostic::Valid, "{}", line);
+        }
+
+        let alphabet: Vec<char> = "(){}/*\"'aé".chars().collect();
+        let mut seed = 0x2545_f491_4f6c_dd1du64;
+        for _ in 0..50_000 {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            let len = (seed % 12) as usize;
+            let line: String = (0..len)
+                .map(|i| alphabet[((seed >> (4 + 4 * i)) % alphabet.len() as u64) as usize])
+                .collect();
+            assert_eq!(
+                code.check(&code.fix(&line).0),
+                Diagnostic::Valid,
+                "{}",
+                line
+            );
+        }
+    }
+}
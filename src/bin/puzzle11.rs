@@ -1,116 +1,575 @@
-use std::{collections::HashMap, error::Error};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    error::Error,
+    hash::{Hash, Hasher},
+    time::Instant,
+};
 use utils::input_parser;
 
-const GRID_SIZE: usize = 10;
-const MAX_ENERGY: u32 = 10;
+const STEPS: usize = 100;
+const MAX_CYCLE_SEARCH_STEPS: usize = 1_000_000;
 
-fn parse_input() -> HashMap<(i32, i32), u32> {
-    let rows = input_parser::parse("puzzle11");
-    let mut octopi = HashMap::new();
-    for (y, row) in rows.iter().enumerate() {
-        for (x, octopus) in row.chars().enumerate() {
-            octopi.insert(
-                (y as i32, x as i32),
-                octopus.to_digit(10).expect("Failed to parse octopus."),
-            );
+/// `(y, x)`
+type Position = (usize, usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Neighbourhood {
+    /// Orthogonal neighbours only
+    Four,
+    /// Orthogonal and diagonal neighbours
+    Eight,
+    /// Hexagonal cells, with odd rows shifted half a cell to the right
+    Hex,
+}
+
+impl Neighbourhood {
+    /// Offsets as `(delta_y, delta_x)` for a cell in row `y`
+    fn offsets(&self, y: usize) -> &'static [(i64, i64)] {
+        match self {
+            Neighbourhood::Four => &[(-1, 0), (1, 0), (0, -1), (0, 1)],
+            Neighbourhood::Eight => &[
+                (-1, 0),
+                (1, 0),
+                (0, -1),
+                (0, 1),
+                (-1, -1),
+                (1, 1),
+                (-1, 1),
+                (1, -1),
+            ],
+            Neighbourhood::Hex if y.is_multiple_of(2) => {
+                &[(-1, -1), (-1, 0), (0, -1), (0, 1), (1, -1), (1, 0)]
+            }
+            Neighbourhood::Hex => &[(-1, 0), (-1, 1), (0, -1), (0, 1), (1, 0), (1, 1)],
         }
     }
+}
 
-    octopi
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Boundary {
+    /// Cells on the edge of the grid have fewer neighbours
+    Bounded,
+    /// The grid wraps around at every edge
+    Toroidal,
 }
 
-fn flash(octopi: &mut HashMap<(i32, i32), u32>, y: i32, x: i32) -> u64 {
-    let mut num_flashes = 1;
-    for (delta_y, delta_x) in [
-        (-1, 0),
-        (1, 0),
-        (0, -1),
-        (0, 1),
-        (-1, -1),
-        (1, 1),
-        (-1, 1),
-        (1, -1),
-    ] {
-        if let Some(energy) = octopi.get_mut(&(y + delta_y, x + delta_x)) {
-            *energy += 1;
-            if *energy == MAX_ENERGY {
-                num_flashes += flash(octopi, y + delta_y, x + delta_x);
-            }
+#[derive(Debug, Clone, Copy)]
+struct Config {
+    /// Energy at which an octopus flashes
+    pub threshold: u32,
+    pub neighbourhood: Neighbourhood,
+    pub boundary: Boundary,
+    /// Number of row bands to step in parallel
+    pub threads: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            threshold: 10,
+            neighbourhood: Neighbourhood::Eight,
+            boundary: Boundary::Bounded,
+            threads: 1,
         }
     }
-    num_flashes
 }
 
-fn part_1(octopi: &mut HashMap<(i32, i32), u32>) -> u64 {
-    let mut flashes = 0;
-    for _ in 0..(GRID_SIZE * GRID_SIZE) {
-        for y in 0..GRID_SIZE {
-            for x in 0..GRID_SIZE {
-                if let Some(energy) = octopi.get_mut(&(y as i32, x as i32)) {
-                    *energy += 1;
-                    if *energy == MAX_ENERGY {
-                        flashes += flash(octopi, y as i32, x as i32);
-                    }
-                }
+#[derive(Clone)]
+struct Simulator {
+    /// Energy levels in row-major order
+    energies: Vec<u32>,
+    width: usize,
+    height: usize,
+    config: Config,
+}
+
+impl Simulator {
+    /// Builds a simulator from rows of digits, taking the grid size from the input.
+    ///
+    /// Energies start below the threshold, so that a cell reaches it exactly once in a step. Any
+    /// octopus already at or past the threshold starts just below it instead, which makes no
+    /// difference since it flashes on its next increment either way.
+    pub fn new(rows: &[String], config: Config) -> Self {
+        assert!(config.threshold > 0, "The threshold must be at least 1");
+        let width = rows.first().map_or(0, |row| row.len());
+        let mut energies = Vec::with_capacity(width * rows.len());
+        for row in rows {
+            assert_eq!(row.len(), width, "All rows must be the same width");
+            for octopus in row.chars() {
+                let energy = octopus.to_digit(10).expect("Failed to parse octopus.");
+                energies.push(energy.min(config.threshold - 1));
             }
         }
 
-        for y in 0..GRID_SIZE {
-            for x in 0..GRID_SIZE {
-                if let Some(energy) =
-                    octopi.get_mut(&(y.try_into().unwrap(), x.try_into().unwrap()))
-                {
-                    if *energy >= MAX_ENERGY {
-                        *energy = 0;
+        Self {
+            energies,
+            width,
+            height: rows.len(),
+            config,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.energies.len()
+    }
+
+    fn neighbours(&self, (y, x): Position) -> impl Iterator<Item = Position> + '_ {
+        let (height, width) = (self.height as i64, self.width as i64);
+        self.config
+            .neighbourhood
+            .offsets(y)
+            .iter()
+            .filter_map(move |(delta_y, delta_x)| {
+                let (y, x) = (y as i64 + delta_y, x as i64 + delta_x);
+                match self.config.boundary {
+                    Boundary::Bounded if y < 0 || y >= height || x < 0 || x >= width => None,
+                    Boundary::Bounded => Some((y as usize, x as usize)),
+                    Boundary::Toroidal => {
+                        Some((y.rem_euclid(height) as usize, x.rem_euclid(width) as usize))
                     }
                 }
+            })
+    }
+
+    /// Flashes every queued cell of one band of rows, starting at `offset` in the grid, and then
+    /// any neighbours pushed over the threshold in turn. Returns the cells that flashed, and the
+    /// increments owed to cells outside the band.
+    fn propagate(
+        &self,
+        band: &mut [u32],
+        offset: usize,
+        mut queue: Vec<usize>,
+    ) -> (Vec<usize>, Vec<usize>) {
+        let mut flashed = Vec::new();
+        let mut outgoing = Vec::new();
+        while let Some(index) = queue.pop() {
+            flashed.push(index);
+            for (y, x) in self.neighbours((index / self.width, index % self.width)) {
+                let neighbour = y * self.width + x;
+                match band.get_mut(neighbour.wrapping_sub(offset)) {
+                    Some(energy) => {
+                        *energy += 1;
+                        if *energy == self.config.threshold {
+                            queue.push(neighbour);
+                        }
+                    }
+                    None => outgoing.push(neighbour),
+                }
             }
         }
+
+        (flashed, outgoing)
     }
 
-    flashes
-}
+    /// Advances the simulation by one step, returning the index of every cell that flashed.
+    ///
+    /// The grid is split into one band of rows per thread. Each band is stepped on its own, then
+    /// flashes that spill over a band edge are handed to the neighbouring band, in rounds, until
+    /// no band has anything left to propagate.
+    fn advance(&mut self) -> Vec<usize> {
+        let mut energies = std::mem::take(&mut self.energies);
+        let band_len = self.height.div_ceil(self.config.threads.max(1)).max(1) * self.width;
+        let mut flashed = Vec::new();
+        let mut incoming: Option<Vec<Vec<usize>>> = None;
+        loop {
+            let bands = energies
+                .chunks_mut(band_len.max(1))
+                .enumerate()
+                .map(|(i, band)| {
+                    let increments = incoming
+                        .as_mut()
+                        .map(|incoming| std::mem::take(&mut incoming[i]));
+                    (band, i * band_len, increments)
+                });
 
-fn part_2(octopi: &mut HashMap<(i32, i32), u32>) -> u64 {
-    for i in 1.. {
-        for y in 0..GRID_SIZE {
-            for x in 0..GRID_SIZE {
-                if let Some(energy) = octopi.get_mut(&(y as i32, x as i32)) {
-                    *energy += 1;
-                    if *energy == MAX_ENERGY {
-                        flash(octopi, y as i32, x as i32);
+            let step_band =
+                |(band, offset, increments): (&mut [u32], usize, Option<Vec<usize>>)| {
+                    let mut queue = Vec::new();
+                    let mut increment = |band: &mut [u32], index: usize| {
+                        let energy = &mut band[index - offset];
+                        *energy += 1;
+                        if *energy == self.config.threshold {
+                            queue.push(index);
+                        }
+                    };
+
+                    match increments {
+                        Some(increments) => increments
+                            .into_iter()
+                            .for_each(|index| increment(band, index)),
+                        None => {
+                            (offset..offset + band.len()).for_each(|index| increment(band, index))
+                        }
                     }
+
+                    self.propagate(band, offset, queue)
+                };
+
+            let results: Vec<(Vec<usize>, Vec<usize>)> = if self.config.threads > 1 {
+                std::thread::scope(|scope| {
+                    let handles: Vec<_> = bands
+                        .map(|band| scope.spawn(move || step_band(band)))
+                        .collect();
+                    handles
+                        .into_iter()
+                        .map(|handle| handle.join().expect("Band thread panicked"))
+                        .collect()
+                })
+            } else {
+                bands.map(step_band).collect()
+            };
+
+            let mut routed = vec![Vec::new(); results.len()];
+            let mut any_outgoing = false;
+            for (mut band_flashed, outgoing) in results {
+                flashed.append(&mut band_flashed);
+                for index in outgoing {
+                    routed[index / band_len].push(index);
+                    any_outgoing = true;
                 }
             }
+
+            if !any_outgoing {
+                break;
+            }
+
+            incoming = Some(routed);
         }
 
-        let mut is_synchronized = true;
-        for y in 0..GRID_SIZE {
-            for x in 0..GRID_SIZE {
-                if let Some(energy) = octopi.get_mut(&(y as i32, x as i32)) {
-                    if *energy >= MAX_ENERGY {
-                        *energy = 0;
-                    } else {
-                        is_synchronized = false;
+        for &index in flashed.iter() {
+            energies[index] = 0;
+        }
+
+        self.energies = energies;
+        flashed
+    }
+
+    /// Advances the simulation by one step, returning every cell that flashed
+    pub fn step(&mut self) -> HashSet<Position> {
+        self.advance()
+            .into_iter()
+            .map(|index| (index / self.width, index % self.width))
+            .collect()
+    }
+
+    fn state_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.energies.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Steps a copy of the simulation until a state repeats, remembering only a hash of each state
+    /// and replaying from the start to rule out hash collisions. Gives up after `max_steps`.
+    pub fn find_cycle(&self, max_steps: usize) -> Option<Cycle> {
+        let mut simulator = self.clone();
+        let mut seen = HashMap::from([(simulator.state_hash(), 0)]);
+        let mut flashes = Vec::new();
+        for step in 1..=max_steps {
+            flashes.push(simulator.advance().len());
+            let hash = simulator.state_hash();
+            match seen.get(&hash) {
+                Some(&earlier) => {
+                    let mut replay = self.clone();
+                    (0..earlier).for_each(|_| {
+                        replay.step();
+                    });
+                    if replay.energies == simulator.energies {
+                        return Some(Cycle {
+                            pre_period: earlier,
+                            period: step - earlier,
+                            flashes,
+                            cells: self.len(),
+                        });
                     }
                 }
+                None => {
+                    seen.insert(hash, step);
+                }
             }
         }
 
-        if is_synchronized {
-            return i;
+        None
+    }
+}
+
+/// The eventually periodic flash history of a simulator. Because every step is deterministic, once
+/// a state repeats the simulation loops forever: the state after step `pre_period` is the same as
+/// after step `pre_period + period`.
+struct Cycle {
+    pub pre_period: usize,
+    pub period: usize,
+    /// Flashes during each step up to the end of the first full cycle, starting with step 1
+    flashes: Vec<usize>,
+    cells: usize,
+}
+
+impl Cycle {
+    /// Number of flashes during the 1-based `step`, or `None` for step 0, before anything has
+    /// happened
+    pub fn flashes_during(&self, step: u64) -> Option<usize> {
+        if step == 0 {
+            return None;
+        }
+
+        let step = if step as usize <= self.flashes.len() {
+            step as usize
+        } else {
+            let offset = (step - self.pre_period as u64 - 1) % self.period as u64;
+            self.pre_period + offset as usize + 1
+        };
+
+        Some(self.flashes[step - 1])
+    }
+
+    /// Total flashes over the first `steps` steps
+    pub fn total_flashes(&self, steps: u64) -> u64 {
+        if steps as usize <= self.flashes.len() {
+            return self.flashes[..steps as usize].iter().sum::<usize>() as u64;
         }
+
+        let pre_period: u64 = self.flashes[..self.pre_period].iter().sum::<usize>() as u64;
+        let cycle = &self.flashes[self.pre_period..];
+        let cycle_sum: u64 = cycle.iter().sum::<usize>() as u64;
+        let remaining = steps - self.pre_period as u64;
+        let partial: u64 = cycle[..(remaining % self.period as u64) as usize]
+            .iter()
+            .sum::<usize>() as u64;
+        pre_period + (remaining / self.period as u64) * cycle_sum + partial
+    }
+
+    /// The first step on which every octopus flashes, or `None` if that never happens. Every
+    /// step after the first cycle repeats one inside it, so checking up to there is a proof.
+    pub fn first_sync(&self) -> Option<u64> {
+        self.flashes
+            .iter()
+            .position(|&flashes| flashes == self.cells)
+            .map(|step| step as u64 + 1)
     }
+}
+
+fn part_1(mut simulator: Simulator) -> usize {
+    (0..STEPS).map(|_| simulator.advance().len()).sum()
+}
 
-    unreachable!();
+fn part_2(cycle: &Cycle) -> Option<u64> {
+    cycle.first_sync()
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let mut part_1_octopi = parse_input();
-    let mut part_2_octopi = part_1_octopi.clone();
+    let rows = input_parser::parse("puzzle11");
+    let simulator = Simulator::new(&rows, Config::default());
 
-    println!("Part 1: {}", part_1(&mut part_1_octopi));
-    println!("Part 2: {}", part_2(&mut part_2_octopi));
+    println!("Part 1: {}", part_1(simulator.clone()));
+    match simulator.find_cycle(MAX_CYCLE_SEARCH_STEPS) {
+        Some(cycle) => {
+            match part_2(&cycle) {
+                Some(step) => println!("Part 2: {}", step),
+                None => println!("Part 2: the octopuses never synchronise"),
+            }
+            println!(
+                "Cycle of period {} after {} steps; flashes after 10^12 steps: {} ({} during the last)",
+                cycle.period,
+                cycle.pre_period,
+                cycle.total_flashes(1_000_000_000_000),
+                cycle
+                    .flashes_during(1_000_000_000_000)
+                    .expect("Only step 0 has no flash count"),
+            );
+        }
+        None => println!(
+            "Part 2: no repeated state within {} steps",
+            MAX_CYCLE_SEARCH_STEPS
+        ),
+    }
+
+    for neighbourhood in [Neighbourhood::Four, Neighbourhood::Hex] {
+        for boundary in [Boundary::Bounded, Boundary::Toroidal] {
+            let config = Config {
+                neighbourhood,
+                boundary,
+                ..Config::default()
+            };
+            println!(
+                "Flashes after {} steps with {:?} neighbours and {:?} edges: {}",
+                STEPS,
+                neighbourhood,
+                boundary,
+                part_1(Simulator::new(&rows, config)),
+            );
+        }
+    }
+
+    // Step a much larger grid, made by tiling the input, with and without row-band threads
+    if std::env::args().any(|arg| arg == "--bench") {
+        let tiled: Vec<String> = rows
+            .iter()
+            .cycle()
+            .take(rows.len() * 100)
+            .map(|row| row.repeat(100))
+            .collect();
+        let threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+        for threads in [1, threads] {
+            let config = Config {
+                threads,
+                ..Config::default()
+            };
+            let start = Instant::now();
+            let flashes = part_1(Simulator::new(&tiled, config));
+            println!(
+                "{}x{} grid on {} thread(s): {} flashes in {:?}",
+                tiled[0].len(),
+                tiled.len(),
+                threads,
+                flashes,
+                start.elapsed(),
+            );
+        }
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: [&str; 10] = [
+        "5483143223",
+        "2745854711",
+        "5264556173",
+        "6141336146",
+        "6357385478",
+        "4167524645",
+        "2176841721",
+        "6882881134",
+        "4846848554",
+        "5283751526",
+    ];
+
+    fn rows(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn example_flashes() {
+        assert_eq!(
+            part_1(Simulator::new(&rows(&EXAMPLE), Config::default())),
+            1656
+        );
+    }
+
+    #[test]
+    fn octopuses_starting_past_the_threshold_flash() {
+        let config = Config {
+            threshold: 5,
+            ..Config::default()
+        };
+        let mut simulator = Simulator::new(&rows(&["797", "979"]), config);
+        assert_eq!(simulator.step().len(), 6);
+
+        // Only how far each octopus is from flashing matters, so anything at or past the
+        // threshold behaves like an octopus one short of it
+        let mut past = Simulator::new(&rows(&["9173", "5902", "6418"]), config);
+        let mut short = Simulator::new(&rows(&["4143", "4402", "4414"]), config);
+        for _ in 0..20 {
+            assert_eq!(past.step(), short.step());
+        }
+    }
+
+    #[test]
+    fn single_octopus_cycles_through_every_energy() {
+        let cycle = Simulator::new(&rows(&["1"]), Config::default())
+            .find_cycle(100)
+            .expect("One octopus repeats within ten steps");
+        assert_eq!((cycle.pre_period, cycle.period), (0, 10));
+        assert_eq!(cycle.flashes_during(0), None);
+        for step in 1..100 {
+            let flashes = usize::from(step % 10 == 9);
+            assert_eq!(cycle.flashes_during(step), Some(flashes), "step {}", step);
+        }
+        assert_eq!(cycle.total_flashes(1_000), 100);
+        assert_eq!(cycle.first_sync(), Some(9));
+    }
+
+    #[test]
+    fn cycle_matches_stepping_directly() {
+        let simulator = Simulator::new(&rows(&EXAMPLE), Config::default());
+        let cycle = simulator
+            .find_cycle(MAX_CYCLE_SEARCH_STEPS)
+            .expect("Example repeats");
+        assert_eq!(cycle.first_sync(), Some(195));
+
+        let mut states = vec![simulator.energies.clone()];
+        let mut flashes = Vec::new();
+        let mut stepped = simulator.clone();
+        for _ in 0..cycle.pre_period + 3 * cycle.period {
+            flashes.push(stepped.advance().len());
+            states.push(stepped.energies.clone());
+        }
+
+        // The cycle starts at the first state that repeats, and repeats as soon as it can
+        assert_eq!(
+            states[cycle.pre_period],
+            states[cycle.pre_period + cycle.period]
+        );
+        if cycle.pre_period > 0 {
+            assert_ne!(
+                states[cycle.pre_period - 1],
+                states[cycle.pre_period - 1 + cycle.period]
+            );
+        }
+        assert!((1..cycle.period)
+            .all(|period| states[cycle.pre_period] != states[cycle.pre_period + period]));
+
+        for (step, &count) in flashes.iter().enumerate() {
+            let step = step as u64 + 1;
+            assert_eq!(cycle.flashes_during(step), Some(count), "step {}", step);
+            assert_eq!(
+                cycle.total_flashes(step),
+                flashes[..step as usize].iter().sum::<usize>() as u64
+            );
+        }
+    }
+
+    #[test]
+    fn threads_do_not_change_the_result() {
+        let tiled: Vec<String> = EXAMPLE
+            .iter()
+            .cycle()
+            .take(EXAMPLE.len() * 3)
+            .map(|row| row.repeat(2))
+            .collect();
+        for neighbourhood in [
+            Neighbourhood::Four,
+            Neighbourhood::Eight,
+            Neighbourhood::Hex,
+        ] {
+            for boundary in [Boundary::Bounded, Boundary::Toroidal] {
+                let config = Config {
+                    neighbourhood,
+                    boundary,
+                    ..Config::default()
+                };
+                let mut single = Simulator::new(&tiled, config);
+                let mut threaded: Vec<Simulator> = [2, 3, 7, 30]
+                    .into_iter()
+                    .map(|threads| Simulator::new(&tiled, Config { threads, ..config }))
+                    .collect();
+                for step in 0..50 {
+                    let expected = single.step();
+                    for simulator in threaded.iter_mut() {
+                        assert_eq!(
+                            simulator.step(),
+                            expected,
+                            "{} threads, step {}, {:?} {:?}",
+                            simulator.config.threads,
+                            step,
+                            neighbourhood,
+                            boundary
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,85 +1,682 @@
 use std::{
     collections::{HashMap, HashSet},
     error::Error,
+    fmt::Write,
+    fs,
 };
 use utils::input_parser;
 
-type Map = HashMap<String, Vec<String>>;
+type CaveId = usize;
+/// Current cave, packed visit counts and whether the single small cave allowance is spent
+type VisitState = (CaveId, u128, bool);
 
-fn parse_input() -> Map {
-    let input = input_parser::parse("puzzle12");
-    let mut edges = Map::new();
-    for line in input.iter() {
-        let (from, to) = line.split_once('-').expect("Failed to parse graph");
-        edges
-            .entry(from.to_owned())
-            .or_default()
-            .push(to.to_owned());
-        edges
-            .entry(to.to_owned())
-            .or_default()
-            .push(from.to_owned());
+/// Cave graph with every name interned to a small integer id
+struct Map {
+    names: Vec<String>,
+    ids: HashMap<String, CaveId>,
+    edges: Vec<Vec<CaveId>>,
+}
+
+impl Map {
+    pub fn new() -> Self {
+        Self {
+            names: Vec::new(),
+            ids: HashMap::new(),
+            edges: Vec::new(),
+        }
+    }
+
+    fn intern(&mut self, name: &str) -> CaveId {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+
+        let id = self.names.len();
+        self.names.push(name.to_owned());
+        self.ids.insert(name.to_owned(), id);
+        self.edges.push(Vec::new());
+        id
+    }
+
+    pub fn add_edge(&mut self, from: &str, to: &str) {
+        let (from, to) = (self.intern(from), self.intern(to));
+        self.edges[from].push(to);
+        self.edges[to].push(from);
+    }
+
+    pub fn id(&self, name: &str) -> Option<CaveId> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn format_path(&self, path: &[CaveId]) -> String {
+        path.iter()
+            .map(|&cave| self.names[cave].as_str())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Renders the cave graph in Graphviz DOT format, with the caves and tunnels of `path`
+    /// highlighted. Big caves are drawn as boxes.
+    pub fn to_dot(&self, path: &[CaveId]) -> String {
+        let on_path: HashSet<CaveId> = path.iter().copied().collect();
+        let path_edges: HashSet<(CaveId, CaveId)> = path
+            .windows(2)
+            .map(|edge| (edge[0].min(edge[1]), edge[0].max(edge[1])))
+            .collect();
+
+        let mut dot = String::from("graph caves {\n");
+        for (cave, name) in self.names.iter().enumerate() {
+            let shape = if is_small(name) { "ellipse" } else { "box" };
+            let style = if on_path.contains(&cave) {
+                ", style=filled, fillcolor=lightcoral"
+            } else {
+                ""
+            };
+            writeln!(dot, "    \"{}\" [shape={}{}];", name, shape, style).unwrap();
+        }
+
+        for (from, edges) in self.edges.iter().enumerate() {
+            for &to in edges.iter().filter(|&&to| from < to) {
+                let style = if path_edges.contains(&(from, to)) {
+                    " [color=red, penwidth=2]"
+                } else {
+                    ""
+                };
+                writeln!(
+                    dot,
+                    "    \"{}\" -- \"{}\"{};",
+                    self.names[from], self.names[to], style
+                )
+                .unwrap();
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
     }
+}
 
-    edges
+fn parse(lines: &[impl AsRef<str>]) -> Map {
+    let mut map = Map::new();
+    for line in lines {
+        let (from, to) = line
+            .as_ref()
+            .split_once('-')
+            .expect("Failed to parse graph");
+        map.add_edge(from, to);
+    }
+
+    map
 }
 
-fn is_small(cave: String) -> bool {
+fn is_small(cave: &str) -> bool {
     cave.chars().next().unwrap().is_lowercase()
 }
 
+/// Which paths count: by default small caves may be visited once and big caves any number of
+/// times, a path starts at `start`, and it ends as soon as it reaches `end`.
+#[derive(Debug, Clone)]
+struct Policy {
+    pub start: String,
+    pub end: String,
+    /// Visit limits for specific caves, overriding the defaults
+    pub limits: HashMap<String, usize>,
+    /// Caves that may not be visited at all
+    pub forbidden: HashSet<String>,
+    /// Lets any one small cave other than `start` and `end` be visited up to this many times
+    pub single_small_cave_limit: Option<usize>,
+}
+
+impl Policy {
+    pub fn new(start: &str, end: &str) -> Self {
+        Self {
+            start: start.to_owned(),
+            end: end.to_owned(),
+            limits: HashMap::new(),
+            forbidden: HashSet::new(),
+            single_small_cave_limit: None,
+        }
+    }
+
+    /// Maximum visits to `cave` ignoring the single small cave allowance, `None` if unlimited
+    fn limit(&self, cave: &str) -> Option<usize> {
+        if self.forbidden.contains(cave) {
+            Some(0)
+        } else if let Some(&limit) = self.limits.get(cave) {
+            Some(limit)
+        } else if is_small(cave) {
+            Some(1)
+        } else {
+            None
+        }
+    }
+}
+
+/// Per-cave visit limits from a [`Policy`], with the visit count of every limited cave packed
+/// into the bits of a `u128` so the visited state of a path can be memoised cheaply.
+struct Rules {
+    start: CaveId,
+    end: CaveId,
+    /// `(base limit, limit with the single small cave allowance, bit offset, bit width)`, or
+    /// `None` for unlimited caves
+    caves: Vec<Option<(usize, usize, u32, u32)>>,
+}
+
+impl Rules {
+    pub fn new(map: &Map, policy: &Policy) -> Result<Self, Box<dyn Error>> {
+        let start = map
+            .id(&policy.start)
+            .ok_or_else(|| format!("No start cave named '{}'", policy.start))?;
+        let end = map
+            .id(&policy.end)
+            .ok_or_else(|| format!("No end cave named '{}'", policy.end))?;
+
+        let mut offset = 0;
+        let mut caves = Vec::new();
+        for (id, name) in map.names.iter().enumerate() {
+            caves.push(policy.limit(name).map(|limit| {
+                let bonus_limit = match policy.single_small_cave_limit {
+                    Some(bonus) if is_small(name) && id != start && id != end && limit > 0 => {
+                        limit.max(bonus)
+                    }
+                    _ => limit,
+                };
+                let width = usize::BITS - bonus_limit.leading_zeros();
+                offset += width;
+                (limit, bonus_limit, offset - width, width)
+            }));
+        }
+
+        if offset > u128::BITS {
+            return Err("Too many limited caves to pack visit counts into 128 bits".into());
+        }
+
+        for (from, edges) in map.edges.iter().enumerate() {
+            if caves[from].is_some() {
+                continue;
+            }
+
+            if let Some(&to) = edges.iter().find(|&&to| caves[to].is_none()) {
+                return Err(format!(
+                    "Caves '{}' and '{}' can be visited forever",
+                    map.names[from], map.names[to]
+                )
+                .into());
+            }
+        }
+
+        Ok(Self { start, end, caves })
+    }
+
+    fn visits(&self, counts: u128, cave: CaveId) -> usize {
+        match self.caves[cave] {
+            Some((_, _, offset, width)) => ((counts >> offset) & ((1 << width) - 1)) as usize,
+            None => 0,
+        }
+    }
+
+    /// The visit state after entering `cave`, or `None` if the policy forbids it
+    fn enter(&self, counts: u128, bonus_used: bool, cave: CaveId) -> Option<(u128, bool)> {
+        let (limit, bonus_limit, offset, _) = match self.caves[cave] {
+            Some(cave) => cave,
+            None => return Some((counts, bonus_used)),
+        };
+
+        let visits = self.visits(counts, cave);
+        let bonus_used = if visits < limit {
+            bonus_used
+        } else if visits < bonus_limit && (!bonus_used || visits > limit) {
+            // Only the cave using the allowance can ever be above its base limit
+            true
+        } else {
+            return None;
+        };
+
+        Some((counts + (1 << offset), bonus_used))
+    }
+}
+
+/// Counts paths from `from` to the end cave, where `counts` holds the visits to each limited cave
+/// so far and `bonus_used` whether the single small cave allowance has been spent. The count
+/// only depends on those three values, so each combination is computed once.
 fn spelunk(
     map: &Map,
-    mut path: Vec<String>,
-    from: String,
-    visited: HashSet<String>,
-    allow_revisit_small_cave: bool,
+    rules: &Rules,
+    from: CaveId,
+    counts: u128,
+    bonus_used: bool,
+    memo: &mut HashMap<VisitState, usize>,
 ) -> usize {
-    path.push(from.clone());
-    if from == "end" {
+    if from == rules.end {
         return 1;
     }
 
+    if let Some(&count) = memo.get(&(from, counts, bonus_used)) {
+        return count;
+    }
+
     let mut count = 0;
-    for to in map.get(&from).expect("Expected a node") {
-        let mut visited = visited.clone();
-        let mut allow_revisit_small_cave = allow_revisit_small_cave;
-        if is_small(to.to_string()) {
-            if visited.contains(to) {
-                if !allow_revisit_small_cave || *to == "start" || *to == "end" {
-                    continue;
+    for &to in map.edges[from].iter() {
+        if let Some((counts, bonus_used)) = rules.enter(counts, bonus_used, to) {
+            count += spelunk(map, rules, to, counts, bonus_used, memo);
+        }
+    }
+
+    memo.insert((from, counts, bonus_used), count);
+    count
+}
+
+/// Extra conditions on the paths yielded by [`Paths`]
+#[derive(Debug, Clone, Default)]
+struct PathFilter {
+    /// Caves every path must pass through
+    pub through: Vec<String>,
+    /// Maximum number of tunnels in a path
+    pub max_length: Option<usize>,
+}
+
+struct Frame {
+    cave: CaveId,
+    counts: u128,
+    bonus_used: bool,
+    /// Index of the next tunnel out of `cave` to try
+    next: usize,
+}
+
+/// Depth first search that yields each path allowed by a policy as it is found
+struct Paths<'a> {
+    map: &'a Map,
+    rules: Rules,
+    through: Vec<CaveId>,
+    max_length: Option<usize>,
+    stack: Vec<Frame>,
+}
+
+impl<'a> Paths<'a> {
+    pub fn new(map: &'a Map, policy: &Policy, filter: PathFilter) -> Result<Self, Box<dyn Error>> {
+        let rules = Rules::new(map, policy)?;
+        let through = filter
+            .through
+            .iter()
+            .map(|name| {
+                map.id(name)
+                    .ok_or_else(|| format!("No cave named '{}'", name))
+            })
+            .collect::<Result<_, _>>()?;
+        let stack = match rules.enter(0, false, rules.start) {
+            Some((counts, bonus_used)) => vec![Frame {
+                cave: rules.start,
+                counts,
+                bonus_used,
+                next: 0,
+            }],
+            None => Vec::new(),
+        };
+
+        Ok(Self {
+            map,
+            rules,
+            through,
+            max_length: filter.max_length,
+            stack,
+        })
+    }
+}
+
+impl Iterator for Paths<'_> {
+    type Item = Vec<CaveId>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let length = self.stack.len().checked_sub(1)?;
+            let frame = self.stack.last_mut()?;
+            if frame.cave == self.rules.end {
+                let path: Vec<CaveId> = self.stack.iter().map(|frame| frame.cave).collect();
+                self.stack.pop();
+                if self.through.iter().all(|cave| path.contains(cave)) {
+                    return Some(path);
                 }
 
-                allow_revisit_small_cave = false;
-            } else {
-                visited.insert(to.to_string());
+                continue;
+            }
+
+            let edges = &self.map.edges[frame.cave];
+            if frame.next == edges.len() || self.max_length.is_some_and(|max| length >= max) {
+                self.stack.pop();
+                continue;
+            }
+
+            let to = edges[frame.next];
+            frame.next += 1;
+            if let Some((counts, bonus_used)) = self.rules.enter(frame.counts, frame.bonus_used, to)
+            {
+                self.stack.push(Frame {
+                    cave: to,
+                    counts,
+                    bonus_used,
+                    next: 0,
+                });
             }
         }
+    }
+}
 
-        count += spelunk(
-            map,
-            path.clone(),
-            to.to_string(),
-            visited,
-            allow_revisit_small_cave,
-        );
+/// Finds the path with the fewest tunnels if `longest` is false, or the most if it is true.
+/// Every move either enters a limited cave, adding to its visits, or enters an unlimited cave
+/// that can only be left for a limited one, so no visit state can repeat along a path and the
+/// best length from each state can be memoised like [`spelunk`].
+fn extreme_path(
+    map: &Map,
+    policy: &Policy,
+    longest: bool,
+) -> Result<Option<Vec<CaveId>>, Box<dyn Error>> {
+    fn best(
+        map: &Map,
+        rules: &Rules,
+        longest: bool,
+        state: VisitState,
+        memo: &mut HashMap<VisitState, Option<(usize, CaveId)>>,
+    ) -> Option<(usize, CaveId)> {
+        let (from, counts, bonus_used) = state;
+        if from == rules.end {
+            return Some((0, from));
+        }
+
+        if let Some(&result) = memo.get(&state) {
+            return result;
+        }
+
+        let mut result: Option<(usize, CaveId)> = None;
+        for &to in map.edges[from].iter() {
+            if let Some((counts, bonus_used)) = rules.enter(counts, bonus_used, to) {
+                if let Some((length, _)) = best(map, rules, longest, (to, counts, bonus_used), memo)
+                {
+                    let is_better = match result {
+                        None => true,
+                        Some((best, _)) if longest => length + 1 > best,
+                        Some((best, _)) => length + 1 < best,
+                    };
+                    if is_better {
+                        result = Some((length + 1, to));
+                    }
+                }
+            }
+        }
+
+        memo.insert(state, result);
+        result
     }
-    count
+
+    let rules = Rules::new(map, policy)?;
+    let mut state = match rules.enter(0, false, rules.start) {
+        Some((counts, bonus_used)) => (rules.start, counts, bonus_used),
+        None => return Ok(None),
+    };
+
+    let mut memo = HashMap::new();
+    let mut path = vec![rules.start];
+    while state.0 != rules.end {
+        let (_, to) = match best(map, &rules, longest, state, &mut memo) {
+            Some(step) => step,
+            None => return Ok(None),
+        };
+        let (counts, bonus_used) = rules
+            .enter(state.1, state.2, to)
+            .expect("Memoised step must be allowed");
+        state = (to, counts, bonus_used);
+        path.push(to);
+    }
+
+    Ok(Some(path))
 }
 
-fn part_1(map: Map) -> usize {
-    let visited = HashSet::from([String::from("start")]);
-    spelunk(&map, Vec::new(), String::from("start"), visited, false)
+fn count_paths(map: &Map, policy: &Policy) -> Result<usize, Box<dyn Error>> {
+    let rules = Rules::new(map, policy)?;
+    Ok(match rules.enter(0, false, rules.start) {
+        Some((counts, bonus_used)) => spelunk(
+            map,
+            &rules,
+            rules.start,
+            counts,
+            bonus_used,
+            &mut HashMap::new(),
+        ),
+        None => 0,
+    })
 }
 
-fn part_2(map: Map) -> usize {
-    let visited = HashSet::from([String::from("start")]);
-    spelunk(&map, Vec::new(), String::from("start"), visited, true)
+fn part_1(map: &Map) -> Result<usize, Box<dyn Error>> {
+    count_paths(map, &Policy::new("start", "end"))
+}
+
+fn part_2(map: &Map) -> Result<usize, Box<dyn Error>> {
+    let mut policy = Policy::new("start", "end");
+    policy.single_small_cave_limit = Some(2);
+    count_paths(map, &policy)
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let map = parse_input();
-    println!("Part 1: {}", part_1(map.clone()));
-    println!("Part 2: {}", part_2(map));
+    let map = parse(&input_parser::parse("puzzle12"));
+    println!("Part 1: {}", part_1(&map)?);
+    println!("Part 2: {}", part_2(&map)?);
+
+    let mut policy = Policy::new("start", "end");
+    policy.single_small_cave_limit = Some(3);
+    println!(
+        "Paths with one small cave visited up to 3 times: {}",
+        count_paths(&map, &policy)?
+    );
+
+    let big_cave = map
+        .names
+        .iter()
+        .find(|name| !is_small(name))
+        .expect("No big caves");
+    let small_cave = map
+        .names
+        .iter()
+        .find(|name| is_small(name) && *name != "start" && *name != "end")
+        .expect("No small caves");
+    let mut policy = Policy::new("start", "end");
+    policy.limits.insert(big_cave.clone(), 2);
+    policy.forbidden.insert(small_cave.clone());
+    println!(
+        "Paths visiting {} at most twice and never {}: {}",
+        big_cave,
+        small_cave,
+        count_paths(&map, &policy)?
+    );
+
+    println!(
+        "Paths from end to start: {}",
+        count_paths(&map, &Policy::new("end", "start"))?
+    );
+
+    let mut part_2_policy = Policy::new("start", "end");
+    part_2_policy.single_small_cave_limit = Some(2);
+    let filter = PathFilter {
+        through: vec![big_cave.clone(), small_cave.clone()],
+        max_length: Some(8),
+    };
+    let mut paths = Paths::new(&map, &part_2_policy, filter)?.peekable();
+    if let Some(path) = paths.peek() {
+        println!(
+            "First path through {} and {} in at most 8 tunnels: {}",
+            big_cave,
+            small_cave,
+            map.format_path(path)
+        );
+    }
+    println!("  {} such paths in total", paths.count());
+
+    let shortest = extreme_path(&map, &part_2_policy, false)?.unwrap_or_default();
+    let longest = extreme_path(&map, &part_2_policy, true)?.unwrap_or_default();
+    println!("Shortest path: {}", map.format_path(&shortest));
+    println!("Longest path: {}", map.format_path(&longest));
+
+    // An optional path to write the cave graph to, with the longest path highlighted
+    if let Some(dot_path) = std::env::args().nth(1) {
+        fs::write(dot_path, map.to_dot(&longest))?;
+    }
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLES: [&[&str]; 3] = [
+        &["start-A", "start-b", "A-c", "A-b", "b-d", "A-end", "b-end"],
+        &[
+            "dc-end", "HN-start", "start-kj", "dc-start", "dc-HN", "LN-dc", "HN-end", "kj-sa",
+            "kj-HN", "kj-dc",
+        ],
+        &[
+            "fs-end", "he-DX", "fs-he", "start-DX", "pj-DX", "end-zg", "zg-sl", "zg-pj", "pj-he",
+            "RW-he", "fs-DX", "pj-RW", "zg-RW", "start-pj", "he-WI", "zg-he", "pj-fs", "start-RW",
+        ],
+    ];
+
+    fn part_2_policy() -> Policy {
+        let mut policy = Policy::new("start", "end");
+        policy.single_small_cave_limit = Some(2);
+        policy
+    }
+
+    /// Counts paths by walking cave names directly, without interning or packed visit counts
+    fn naive_count(map: &Map, policy: &Policy) -> usize {
+        fn walk(
+            map: &Map,
+            policy: &Policy,
+            cave: &str,
+            visits: &mut HashMap<String, usize>,
+            bonus_cave: Option<&str>,
+        ) -> usize {
+            let count = visits.get(cave).copied().unwrap_or(0) + 1;
+            let bonus_cave = match policy.limit(cave) {
+                Some(limit) if count > limit => {
+                    let eligible = is_small(cave)
+                        && cave != policy.start
+                        && cave != policy.end
+                        && limit > 0
+                        && bonus_cave.is_none_or(|bonus| bonus == cave);
+                    match policy.single_small_cave_limit {
+                        Some(bonus) if eligible && count <= bonus => Some(cave),
+                        _ => return 0,
+                    }
+                }
+                _ => bonus_cave,
+            };
+            if cave == policy.end {
+                return 1;
+            }
+
+            visits.insert(cave.to_owned(), count);
+            let id = map.id(cave).expect("Cave is on the map");
+            let paths = map.edges[id]
+                .iter()
+                .map(|&to| walk(map, policy, &map.names[to], visits, bonus_cave))
+                .sum();
+            visits.insert(cave.to_owned(), count - 1);
+            paths
+        }
+
+        walk(map, policy, &policy.start, &mut HashMap::new(), None)
+    }
+
+    #[test]
+    fn examples() {
+        for (lines, expected) in EXAMPLES.iter().zip([(10, 36), (19, 103), (226, 3509)]) {
+            let map = parse(lines);
+            assert_eq!((part_1(&map).unwrap(), part_2(&map).unwrap()), expected);
+        }
+    }
+
+    #[test]
+    fn custom_rules_match_walking_by_name() {
+        let map = parse(EXAMPLES[0]);
+        let mut policy = Policy::new("start", "end");
+        policy.limits.insert(String::from("A"), 3);
+        policy.limits.insert(String::from("c"), 2);
+        policy.forbidden.insert(String::from("d"));
+        policy.single_small_cave_limit = Some(3);
+
+        let count = count_paths(&map, &policy).unwrap();
+        assert_eq!(count, naive_count(&map, &policy));
+        assert_eq!(
+            Paths::new(&map, &policy, PathFilter::default())
+                .unwrap()
+                .count(),
+            count
+        );
+
+        let looping = parse(&["start-A", "A-B", "B-end"]);
+        assert!(count_paths(&looping, &Policy::new("start", "end")).is_err());
+        assert!(count_paths(&map, &Policy::new("start", "exit")).is_err());
+    }
+
+    #[test]
+    fn iterated_paths_match_memoised_counts() {
+        for lines in EXAMPLES {
+            let map = parse(lines);
+            for policy in [Policy::new("start", "end"), part_2_policy()] {
+                let paths: Vec<Vec<CaveId>> = Paths::new(&map, &policy, PathFilter::default())
+                    .unwrap()
+                    .collect();
+                assert_eq!(paths.len(), count_paths(&map, &policy).unwrap());
+                assert_eq!(paths.len(), naive_count(&map, &policy));
+
+                let distinct: HashSet<&Vec<CaveId>> = paths.iter().collect();
+                assert_eq!(distinct.len(), paths.len());
+
+                let lengths = paths.iter().map(Vec::len);
+                let shortest = extreme_path(&map, &policy, false).unwrap().unwrap();
+                let longest = extreme_path(&map, &policy, true).unwrap().unwrap();
+                assert_eq!(Some(shortest.len()), lengths.clone().min());
+                assert_eq!(Some(longest.len()), lengths.max());
+                assert!(paths.contains(&shortest) && paths.contains(&longest));
+            }
+        }
+    }
+
+    #[test]
+    fn filtered_paths_are_the_matching_unfiltered_paths() {
+        let map = parse(EXAMPLES[2]);
+        let policy = part_2_policy();
+        let through = vec![String::from("RW"), String::from("sl")];
+        let ids: Vec<CaveId> = through.iter().map(|name| map.id(name).unwrap()).collect();
+        let expected: Vec<Vec<CaveId>> = Paths::new(&map, &policy, PathFilter::default())
+            .unwrap()
+            .filter(|path| path.len() <= 9 && ids.iter().all(|id| path.contains(id)))
+            .collect();
+        let filter = PathFilter {
+            through,
+            max_length: Some(8),
+        };
+        let paths: Vec<Vec<CaveId>> = Paths::new(&map, &policy, filter).unwrap().collect();
+        assert!(!paths.is_empty());
+        assert_eq!(paths, expected);
+    }
+
+    #[test]
+    fn dot_output_highlights_the_path() {
+        let map = parse(EXAMPLES[0]);
+        let path = extreme_path(&map, &Policy::new("start", "end"), true)
+            .unwrap()
+            .unwrap();
+        let dot = map.to_dot(&path);
+        assert!(dot.starts_with("graph caves {\n") && dot.ends_with("}\n"));
+        // The longest path goes back and forth along some tunnels, which are only drawn once
+        let caves: HashSet<CaveId> = path.iter().copied().collect();
+        let tunnels: HashSet<(CaveId, CaveId)> = path
+            .windows(2)
+            .map(|tunnel| (tunnel[0].min(tunnel[1]), tunnel[0].max(tunnel[1])))
+            .collect();
+        assert!(tunnels.len() < path.len() - 1);
+        assert_eq!(dot.matches("fillcolor").count(), caves.len());
+        assert_eq!(dot.matches("color=red").count(), tunnels.len());
+        assert_eq!(dot.matches(" -- ").count(), EXAMPLES[0].len());
+        assert!(dot.contains("\"A\" [shape=box"));
+    }
+}
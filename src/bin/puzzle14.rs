@@ -1,81 +1,645 @@
-use std::{collections::HashMap, error::Error};
-use utils::input_parser;
-
-pub fn count(
-    polymer1: char,
-    polymer2: char,
-    reactions: &HashMap<(char, char), char>,
-    memo: &mut HashMap<(char, char, usize), HashMap<char, usize>>,
-    iterations: usize,
-) -> HashMap<char, usize> {
-    if iterations == 0 {
-        let mut counts = HashMap::new();
-        *counts.entry(polymer1).or_default() += 1;
-        *counts.entry(polymer2).or_default() += 1;
-        return counts;
-    }
-
-    if let Some(result) = memo.get(&(polymer1, polymer2, iterations)) {
-        return result.clone();
-    }
-
-    let compound_option = reactions.get(&(polymer1, polymer2));
-    if compound_option.is_none() {
-        let mut counts = HashMap::new();
-        *counts.entry(polymer1).or_default() += 1;
-        *counts.entry(polymer2).or_default() += 1;
-        return counts;
-    }
-
-    let compound = compound_option.unwrap();
-    let mut counts = HashMap::new();
-    count(polymer1, *compound, reactions, memo, iterations - 1)
-        .into_iter()
-        .for_each(|(c, count)| *counts.entry(c).or_default() += count);
-    count(*compound, polymer2, reactions, memo, iterations - 1)
-        .into_iter()
-        .for_each(|(c, count)| *counts.entry(c).or_default() += count);
-    *counts.entry(*compound).or_default() -= 1;
-    memo.insert((polymer1, polymer2, iterations), counts.clone());
-    counts
-}
-
-fn solve(input: &[String], num_iterations: usize) -> usize {
-    let template: Vec<char> = input[0].chars().collect();
-    let mut reactions = HashMap::new();
-
-    for line in input.iter().skip(2) {
-        let (pattern, addition) = line.split_once(" -> ").unwrap();
-        let mut pattern = pattern.chars();
-        let p1 = pattern.next().unwrap();
-        let p2 = pattern.next().unwrap();
-        let addition = addition.chars().next().unwrap();
-        reactions.insert((p1, p2), addition);
-    }
-
-    let mut memo: HashMap<(char, char, usize), HashMap<char, usize>> = HashMap::new();
-    let mut counts: HashMap<char, usize> = HashMap::new();
-
-    for i in 1..template.len() {
-        let p1 = template[i - 1];
-        let p2 = template[i];
-        for (c, count) in count(p1, p2, &reactions, &mut memo, num_iterations) {
-            *counts.entry(c).or_default() += count;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    error::Error,
+    fmt::Display,
+};
+use utils::{big_uint::BigUint, input_parser};
+
+/// Counts that the transition matrix can be built from. Primitive integers report overflow so
+/// the caller can retry with [`BigUint`].
+trait Count: Clone {
+    fn zero() -> Self;
+    fn one() -> Self;
+    fn is_zero(&self) -> bool;
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+    fn checked_mul(&self, other: &Self) -> Option<Self>;
+}
+
+impl Count for u128 {
+    fn zero() -> Self {
+        0
+    }
+
+    fn one() -> Self {
+        1
+    }
+
+    fn is_zero(&self) -> bool {
+        *self == 0
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        u128::checked_add(*self, *other)
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        u128::checked_mul(*self, *other)
+    }
+}
+
+impl Count for BigUint {
+    fn zero() -> Self {
+        BigUint::zero()
+    }
+
+    fn one() -> Self {
+        BigUint::one()
+    }
+
+    fn is_zero(&self) -> bool {
+        BigUint::is_zero(self)
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(self + other)
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        Some(self * other)
+    }
+}
+
+/// Square matrix where entry `[i][j]` is how many pairs `j` a single pair `i` turns into
+#[derive(Clone)]
+struct Matrix<T>(Vec<Vec<T>>);
+
+impl<T: Count> Matrix<T> {
+    fn identity(size: usize) -> Self {
+        Self(
+            (0..size)
+                .map(|i| {
+                    (0..size)
+                        .map(|j| if i == j { T::one() } else { T::zero() })
+                        .collect()
+                })
+                .collect(),
+        )
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        let size = self.0.len();
+        let mut product = vec![vec![T::zero(); size]; size];
+        for (i, row) in self.0.iter().enumerate() {
+            for (k, a) in row.iter().enumerate() {
+                // Transition matrices are mostly zeros for the first few steps
+                if a.is_zero() {
+                    continue;
+                }
+
+                for (j, b) in other.0[k].iter().enumerate() {
+                    product[i][j] = product[i][j].checked_add(&a.checked_mul(b)?)?;
+                }
+            }
+        }
+
+        Some(Self(product))
+    }
+
+    /// Raises the matrix to `exponent` by repeated squaring
+    fn checked_pow(&self, mut exponent: u64) -> Option<Self> {
+        let mut result = Self::identity(self.0.len());
+        let mut base = self.clone();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result.checked_mul(&base)?;
+            }
+
+            exponent >>= 1;
+            if exponent > 0 {
+                base = base.checked_mul(&base)?;
+            }
+        }
+
+        Some(result)
+    }
+}
+
+/// How many of each element a polymer contains
+#[derive(Debug, Clone)]
+struct Histogram {
+    counts: BTreeMap<char, BigUint>,
+}
+
+impl Histogram {
+    pub fn get(&self, element: char) -> BigUint {
+        self.counts.get(&element).cloned().unwrap_or_default()
+    }
+
+    pub fn total(&self) -> BigUint {
+        self.counts
+            .values()
+            .fold(BigUint::zero(), |total, count| &total + count)
+    }
+
+    /// Elements that are present, ordered from most to least common
+    pub fn ranked(&self) -> Vec<(char, &BigUint)> {
+        let mut ranked: Vec<(char, &BigUint)> = self
+            .counts
+            .iter()
+            .filter(|(_, count)| !count.is_zero())
+            .map(|(&element, count)| (element, count))
+            .collect();
+        ranked.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(&b.0)));
+        ranked
+    }
+
+    /// Quantity of the most common element minus the quantity of the least common element
+    pub fn spread(&self) -> BigUint {
+        let ranked = self.ranked();
+        match (ranked.first(), ranked.last()) {
+            (Some((_, most)), Some((_, least))) => most
+                .checked_sub(least)
+                .expect("Most common is at least least common"),
+            _ => BigUint::zero(),
+        }
+    }
+}
+
+/// The pairs that `(a, b)` turns into after one step. A pair without a rule stays as it is.
+fn produced(reactions: &HashMap<(char, char), String>, (a, b): (char, char)) -> Vec<(char, char)> {
+    let insert = reactions.get(&(a, b)).map_or("", String::as_str);
+    let elements: Vec<char> = std::iter::once(a)
+        .chain(insert.chars())
+        .chain(std::iter::once(b))
+        .collect();
+    elements.windows(2).map(|pair| (pair[0], pair[1])).collect()
+}
+
+/// Long-run behaviour of a polymer as the number of steps grows
+#[derive(Debug, Clone)]
+struct Asymptotics {
+    /// Factor the polymer length is multiplied by each step
+    pub growth_rate: f64,
+    /// Share of the polymer made up by each element
+    pub proportions: BTreeMap<char, f64>,
+    /// Elements whose count stops growing
+    pub bounded: Vec<char>,
+    /// Elements whose count keeps growing, but more slowly than the polymer, so their share
+    /// tends to zero
+    pub vanishing: Vec<char>,
+}
+
+/// A polymer template and its insertion rules, tracked as counts of adjacent element pairs
+struct Polymer {
+    template: Vec<char>,
+    /// Elements inserted between each pair, in order
+    reactions: HashMap<(char, char), String>,
+    elements: Vec<char>,
+    /// Pair `(a, b)` has index `index[a] * elements.len() + index[b]`
+    index: HashMap<char, usize>,
+    transitions: Matrix<u128>,
+}
+
+impl Polymer {
+    pub fn new(template: &str, reactions: HashMap<(char, char), String>) -> Self {
+        let template: Vec<char> = template.chars().collect();
+        let mut elements: Vec<char> = template
+            .iter()
+            .copied()
+            .chain(
+                reactions
+                    .iter()
+                    .flat_map(|(&(a, b), insert)| [a, b].into_iter().chain(insert.chars())),
+            )
+            .collect();
+        elements.sort_unstable();
+        elements.dedup();
+        let index: HashMap<char, usize> = elements
+            .iter()
+            .enumerate()
+            .map(|(i, &element)| (element, i))
+            .collect();
+
+        let size = elements.len();
+        let mut transitions = Matrix(vec![vec![0; size * size]; size * size]);
+        for &a in elements.iter() {
+            for &b in elements.iter() {
+                let pair = index[&a] * size + index[&b];
+                for (c, d) in produced(&reactions, (a, b)) {
+                    transitions.0[pair][index[&c] * size + index[&d]] += 1;
+                }
+            }
+        }
+
+        Self {
+            template,
+            reactions,
+            elements,
+            index,
+            transitions,
+        }
+    }
+
+    /// Every pair that can occur in the polymer at some step
+    fn reachable_pairs(&self, from: impl Iterator<Item = (char, char)>) -> HashSet<(char, char)> {
+        let mut seen = HashSet::new();
+        let mut edge: Vec<(char, char)> = from.collect();
+        while let Some(pair) = edge.pop() {
+            if seen.insert(pair) {
+                edge.extend(produced(&self.reactions, pair));
+            }
+        }
+
+        seen
+    }
+
+    fn template_pairs(&self) -> impl Iterator<Item = (char, char)> + '_ {
+        self.template.windows(2).map(|pair| (pair[0], pair[1]))
+    }
+
+    /// Pairs that can occur in the polymer at some step but have no insertion rule
+    pub fn pairs_without_rules(&self) -> Vec<(char, char)> {
+        let mut missing: Vec<(char, char)> = self
+            .reachable_pairs(self.template_pairs())
+            .into_iter()
+            .filter(|pair| !self.reactions.contains_key(pair))
+            .collect();
+        missing.sort_unstable();
+        missing
+    }
+
+    /// Finds the growth rate and element proportions the polymer tends towards, from the
+    /// dominant eigenvector of the pair transition matrix restricted to the template's pairs. A
+    /// polymer that stops growing is counted exactly instead.
+    ///
+    /// Power iteration is run on `T + I` rather than `T`: both share eigenvectors, but the shift
+    /// stops the iteration oscillating when `T` has several eigenvalues of the same magnitude.
+    pub fn asymptotics(&self) -> Asymptotics {
+        const MAX_ITERATIONS: usize = 100_000;
+        const TOLERANCE: f64 = 1e-13;
+        const NEGLIGIBLE_SHARE: f64 = 1e-9;
+
+        // A pair keeps turning up forever if it can be reached from a cycle of pairs that insert
+        // something, and an element's count only grows while such a pair keeps inserting it
+        let reachable = self.reachable_pairs(self.template_pairs());
+        let cyclic = reachable.iter().copied().filter(|&pair| {
+            self.reactions
+                .get(&pair)
+                .is_some_and(|insert| !insert.is_empty())
+                && self
+                    .reachable_pairs(produced(&self.reactions, pair).into_iter())
+                    .contains(&pair)
+        });
+        let recurrent = self.reachable_pairs(cyclic);
+
+        let mut proportions: BTreeMap<char, f64> = BTreeMap::new();
+        let growth_rate = if recurrent.is_empty() {
+            // Without such a cycle, every chain of insertions ends within as many steps as there
+            // are pairs, and the polymer stops growing, so its final makeup can be counted
+            // exactly. This also covers a template too short to have any pairs.
+            let histogram = self.histogram(reachable.len() as u64);
+            let total = histogram.total().to_u128().expect("Polymer stops growing") as f64;
+            for (element, count) in histogram.ranked() {
+                let count = count.to_u128().expect("Polymer stops growing") as f64;
+                proportions.insert(element, count / total);
+            }
+            1.0
+        } else {
+            let size = self.elements.len();
+            let transitions: Vec<Vec<f64>> = self
+                .transitions
+                .0
+                .iter()
+                .map(|row| row.iter().map(|&count| count as f64).collect())
+                .collect();
+            let apply = |vector: &[f64]| -> Vec<f64> {
+                let mut next = vec![0.0; vector.len()];
+                for (from, weight) in vector.iter().enumerate().filter(|(_, &w)| w != 0.0) {
+                    for (to, count) in transitions[from].iter().enumerate() {
+                        next[to] += weight * count;
+                    }
+                }
+                next
+            };
+            let normalized = |vector: Vec<f64>| -> Vec<f64> {
+                let total: f64 = vector.iter().sum();
+                vector.into_iter().map(|value| value / total).collect()
+            };
+
+            let mut vector = vec![0.0; size * size];
+            for (a, b) in self.template_pairs() {
+                vector[self.index[&a] * size + self.index[&b]] += 1.0;
+            }
+            vector = normalized(vector);
+
+            for _ in 0..MAX_ITERATIONS {
+                let shifted: Vec<f64> = apply(&vector)
+                    .iter()
+                    .zip(vector.iter())
+                    .map(|(applied, value)| applied + value)
+                    .collect();
+                let next = normalized(shifted);
+                let change = next
+                    .iter()
+                    .zip(vector.iter())
+                    .map(|(a, b)| (a - b).abs())
+                    .fold(0.0, f64::max);
+                vector = next;
+                if change < TOLERANCE {
+                    break;
+                }
+            }
+
+            // Each element is counted as the first of a pair. That misses only the last element
+            // of the polymer, whose single count is negligible as the polymer grows without bound.
+            for (pair, share) in vector.iter().enumerate() {
+                *proportions.entry(self.elements[pair / size]).or_default() += share;
+            }
+            apply(&vector).iter().sum()
+        };
+        let growing: HashSet<char> = recurrent
+            .iter()
+            .filter_map(|pair| self.reactions.get(pair))
+            .flat_map(|insert| insert.chars())
+            .collect();
+        let present: HashSet<char> = reachable
+            .iter()
+            .flat_map(|&(a, b)| [a, b])
+            .chain(self.template.iter().copied())
+            .collect();
+
+        let mut bounded: Vec<char> = present
+            .iter()
+            .copied()
+            .filter(|element| !growing.contains(element))
+            .collect();
+        bounded.sort_unstable();
+        let mut vanishing: Vec<char> = growing
+            .iter()
+            .copied()
+            .filter(|element| proportions.get(element).copied().unwrap_or(0.0) < NEGLIGIBLE_SHARE)
+            .collect();
+        vanishing.sort_unstable();
+        proportions.retain(|element, _| present.contains(element));
+
+        Asymptotics {
+            growth_rate,
+            proportions,
+            bounded,
+            vanishing,
         }
+    }
+
+    /// Builds the literal polymer after `steps` steps, for debugging. Returns `None` if it would
+    /// be longer than `max_len` elements.
+    pub fn expand(&self, steps: usize, max_len: usize) -> Option<String> {
+        let mut polymer = self.template.clone();
+        for _ in 0..steps {
+            let mut next = Vec::with_capacity(polymer.len() * 2);
+            for pair in polymer.windows(2) {
+                next.push(pair[0]);
+                if let Some(insert) = self.reactions.get(&(pair[0], pair[1])) {
+                    next.extend(insert.chars());
+                }
+            }
+            next.extend(polymer.last());
 
-        if i != 1 {
-            *counts.entry(p1).or_default() -= 1;
+            if next.len() > max_len {
+                return None;
+            }
+            polymer = next;
         }
+
+        Some(polymer.into_iter().collect())
     }
 
-    let most_common = counts.values().max().unwrap();
-    let least_common = counts.values().min().unwrap();
-    most_common - least_common
+    fn pair_counts<T: Count>(&self, transitions: &Matrix<T>) -> Option<Vec<T>> {
+        let size = self.elements.len();
+        let mut counts = vec![T::zero(); size * size];
+        for pair in self.template.windows(2) {
+            let from = self.index[&pair[0]] * size + self.index[&pair[1]];
+            for (to, count) in transitions.0[from].iter().enumerate() {
+                counts[to] = counts[to].checked_add(count)?;
+            }
+        }
+
+        Some(counts)
+    }
+
+    /// Counts every element after `steps` insertion steps, in `O(log steps)` matrix products.
+    /// Counts are exact: the products fall back to big integers if they overflow `u128`.
+    pub fn histogram(&self, steps: u64) -> Histogram {
+        let pair_counts: Vec<BigUint> = match self
+            .transitions
+            .checked_pow(steps)
+            .and_then(|transitions| self.pair_counts(&transitions))
+        {
+            Some(counts) => counts.into_iter().map(BigUint::from).collect(),
+            None => {
+                let transitions = Matrix(
+                    self.transitions
+                        .0
+                        .iter()
+                        .map(|row| row.iter().map(|&count| BigUint::from(count)).collect())
+                        .collect(),
+                );
+                self.pair_counts(
+                    &transitions
+                        .checked_pow(steps)
+                        .expect("Big integers never overflow"),
+                )
+                .expect("Big integers never overflow")
+            }
+        };
+
+        // Every element is the first of a pair, except the last one, which never changes
+        let size = self.elements.len();
+        let mut counts: BTreeMap<char, BigUint> = BTreeMap::new();
+        for (pair, count) in pair_counts.iter().enumerate() {
+            *counts.entry(self.elements[pair / size]).or_default() += count;
+        }
+
+        if let Some(&last) = self.template.last() {
+            *counts.entry(last).or_default() += &BigUint::one();
+        }
+
+        Histogram { counts }
+    }
+}
+
+#[derive(Debug)]
+enum ParseRuleError {
+    NoDelimeter(usize),
+    /// A rule must match exactly two elements
+    BadPattern(usize),
+    Conflict {
+        line: usize,
+        pattern: (char, char),
+        first: String,
+        second: String,
+    },
+}
+
+impl Display for ParseRuleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseRuleError::NoDelimeter(line) => {
+                write!(f, "No ' -> ' delimeter found on line {}", line)
+            }
+            ParseRuleError::BadPattern(line) => {
+                write!(f, "Rule on line {} must match exactly two elements", line)
+            }
+            ParseRuleError::Conflict {
+                line,
+                pattern,
+                first,
+                second,
+            } => write!(
+                f,
+                "Rule on line {} inserts '{}' between {}{}, but an earlier rule inserts '{}'",
+                line, second, pattern.0, pattern.1, first
+            ),
+        }
+    }
+}
+
+impl Error for ParseRuleError {}
+
+/// Parses the template and its rules, which may insert any number of elements. Returns the
+/// polymer along with the patterns of any rules that were repeated word for word.
+fn parse(input: &[String]) -> Result<(Polymer, Vec<(char, char)>), ParseRuleError> {
+    let mut reactions: HashMap<(char, char), String> = HashMap::new();
+    let mut duplicates = Vec::new();
+
+    for (i, line) in input.iter().enumerate().skip(2) {
+        let line_num = i + 1;
+        let (pattern, insert) = line
+            .split_once(" -> ")
+            .ok_or(ParseRuleError::NoDelimeter(line_num))?;
+        let pattern = match pattern.chars().collect::<Vec<_>>()[..] {
+            [a, b] => (a, b),
+            _ => return Err(ParseRuleError::BadPattern(line_num)),
+        };
+
+        match reactions.get(&pattern) {
+            Some(existing) if existing == insert => duplicates.push(pattern),
+            Some(existing) => {
+                return Err(ParseRuleError::Conflict {
+                    line: line_num,
+                    pattern,
+                    first: existing.clone(),
+                    second: insert.to_owned(),
+                })
+            }
+            None => {
+                reactions.insert(pattern, insert.to_owned());
+            }
+        }
+    }
+
+    Ok((Polymer::new(&input[0], reactions), duplicates))
+}
+
+fn solve(polymer: &Polymer, num_iterations: u64) -> BigUint {
+    polymer.histogram(num_iterations).spread()
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let input = input_parser::parse("puzzle14");
-    println!("Part 1: {}", solve(&input, 10));
-    println!("Part 1: {}", solve(&input, 40));
+    let (polymer, duplicates) = parse(&input)?;
+    for (a, b) in duplicates {
+        println!("Warning: duplicate rule for {}{}", a, b);
+    }
+    for (a, b) in polymer.pairs_without_rules() {
+        println!("Warning: pair {}{} can occur but has no rule", a, b);
+    }
+
+    println!("Part 1: {}", solve(&polymer, 10));
+    println!("Part 2: {}", solve(&polymer, 40));
+
+    for steps in 0..=2 {
+        let literal = polymer
+            .expand(steps, 1_000)
+            .expect("Polymer is small for a few steps");
+        println!("After step {}: {}", steps, literal);
+    }
+
+    let asymptotics = polymer.asymptotics();
+    println!(
+        "Polymer grows by a factor of {:.6} per step; long-run shares:",
+        asymptotics.growth_rate
+    );
+    for (element, share) in asymptotics.proportions.iter() {
+        println!("  {}: {:.6}", element, share);
+    }
+    println!(
+        "Bounded elements: {:?}; vanishing elements: {:?}",
+        asymptotics.bounded, asymptotics.vanishing
+    );
+
+    let histogram = polymer.histogram(150);
+    println!("After 150 steps ({} elements):", histogram.total());
+    for (element, count) in histogram.ranked() {
+        println!("  {}: {}", element, count);
+    }
+    println!(
+        "  the template's first element, {}, appears {} times",
+        polymer.template[0],
+        histogram.get(polymer.template[0])
+    );
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn polymer(lines: &[&str]) -> Polymer {
+        let input: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+        parse(&input).expect("Rules are valid").0
+    }
+
+    #[test]
+    fn literal_polymer_agrees_with_pair_counts() {
+        let polymer = polymer(&[
+            "NNCB", "", "CH -> B", "HH -> N", "CB -> H", "NH -> C", "HB -> C", "HC -> B",
+            "HN -> C", "NN -> C", "BH -> H", "NC -> B", "NB -> B", "BN -> B", "BB -> N", "BC -> B",
+            "CC -> N", "CN -> C",
+        ]);
+        for steps in 0..=6 {
+            let literal = polymer
+                .expand(steps, 1_000)
+                .expect("Polymer is still small");
+            let histogram = polymer.histogram(steps as u64);
+            for element in literal.chars() {
+                let count = literal.matches(element).count() as u128;
+                assert_eq!(
+                    histogram.get(element).to_u128(),
+                    Some(count),
+                    "step {}",
+                    steps
+                );
+            }
+            assert_eq!(histogram.total().to_u128(), Some(literal.len() as u128));
+        }
+        assert_eq!(solve(&polymer, 10).to_u128(), Some(1588));
+    }
+
+    #[test]
+    fn shares_of_a_polymer_that_stops_growing_count_every_element() {
+        let polymer = polymer(&["ABA", "", "AB -> XYZ", "XY -> A"]);
+        let asymptotics = polymer.asymptotics();
+        assert!((asymptotics.proportions[&'A'] - 3.0 / 7.0).abs() < 1e-12);
+        assert!((asymptotics.proportions.values().sum::<f64>() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn shares_of_a_polymer_whose_rules_insert_nothing_are_its_template() {
+        let asymptotics = polymer(&["AB", "", "AB -> "]).asymptotics();
+        assert_eq!(asymptotics.growth_rate, 1.0);
+        assert_eq!(
+            asymptotics.proportions,
+            BTreeMap::from([('A', 0.5), ('B', 0.5)])
+        );
+        assert_eq!(asymptotics.bounded, vec!['A', 'B']);
+    }
+
+    #[test]
+    fn shares_of_a_single_element_template_are_all_that_element() {
+        let asymptotics = polymer(&["A", "", "AB -> C"]).asymptotics();
+        assert_eq!(asymptotics.growth_rate, 1.0);
+        assert_eq!(asymptotics.proportions, BTreeMap::from([('A', 1.0)]));
+        assert_eq!(asymptotics.bounded, vec!['A']);
+    }
+}
@@ -1,12 +1,120 @@
 use std::{
     cmp::Ordering,
-    collections::{BinaryHeap, HashMap},
+    collections::{BinaryHeap, HashMap, HashSet},
     error::Error,
+    fmt::Display,
+    time::Instant,
 };
 use utils::input_parser;
 
+/// `(row, column)`
 type Position = (usize, usize);
 
+/// A grid of risk levels, where `None` marks a cell that cannot be entered
+type RiskMap = Vec<Vec<Option<u32>>>;
+
+trait Grid {
+    fn height(&self) -> usize;
+    fn width(&self) -> usize;
+    /// Risk of entering `position`, or `None` if it is a wall or off the map
+    fn risk(&self, position: Position) -> Option<u32>;
+}
+
+impl Grid for RiskMap {
+    fn height(&self) -> usize {
+        self.len()
+    }
+
+    fn width(&self) -> usize {
+        self.first().map_or(0, |row| row.len())
+    }
+
+    fn risk(&self, (row, column): Position) -> Option<u32> {
+        self.get(row)?.get(column).copied().flatten()
+    }
+}
+
+#[derive(Debug)]
+enum MapError {
+    /// Risk levels start at 1, so a 0 cannot be wrapped round like the others
+    ZeroRisk(Position),
+    ZeroModulus,
+}
+
+impl Display for MapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MapError::ZeroRisk((row, column)) => {
+                write!(f, "Risk level 0 at row {}, column {}", row, column)
+            }
+            MapError::ZeroModulus => write!(f, "Risk levels cannot wrap round above 0"),
+        }
+    }
+}
+
+impl Error for MapError {}
+
+/// A map made of `factor` by `factor` copies of a tile, where each copy's risk levels are raised
+/// by `increment` for every tile it is to the right of or below the original, wrapping back
+/// round to 1 above `modulus`. Risk levels are worked out on demand rather than stored.
+struct TiledMap<'a> {
+    tile: &'a RiskMap,
+    factor: usize,
+    increment: u32,
+    modulus: u32,
+}
+
+impl<'a> TiledMap<'a> {
+    /// Tiles the map as in the puzzle, raising risk levels by 1 per tile and wrapping above 9
+    pub fn new(tile: &'a RiskMap, factor: usize) -> Result<Self, MapError> {
+        Self::with_wrapping(tile, factor, 1, 9)
+    }
+
+    pub fn with_wrapping(
+        tile: &'a RiskMap,
+        factor: usize,
+        increment: u32,
+        modulus: u32,
+    ) -> Result<Self, MapError> {
+        if modulus == 0 {
+            return Err(MapError::ZeroModulus);
+        }
+        for (row, risks) in tile.iter().enumerate() {
+            if let Some(column) = risks.iter().position(|&risk| risk == Some(0)) {
+                return Err(MapError::ZeroRisk((row, column)));
+            }
+        }
+
+        Ok(Self {
+            tile,
+            factor,
+            increment,
+            modulus,
+        })
+    }
+}
+
+impl Grid for TiledMap<'_> {
+    fn height(&self) -> usize {
+        self.tile.height() * self.factor
+    }
+
+    fn width(&self) -> usize {
+        self.tile.width() * self.factor
+    }
+
+    fn risk(&self, (row, column): Position) -> Option<u32> {
+        if row >= self.height() || column >= self.width() {
+            return None;
+        }
+
+        let (tile_height, tile_width) = (self.tile.height(), self.tile.width());
+        let tile_distance = (row / tile_height + column / tile_width) as u32;
+        let risk = self.tile.risk((row % tile_height, column % tile_width))?;
+        Some((risk - 1 + tile_distance * self.increment) % self.modulus + 1)
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 struct Node {
     position: Position,
@@ -28,7 +136,181 @@ impl PartialOrd for Node {
     }
 }
 
-fn adjacencies(position: (usize, usize), height: usize, width: usize) -> Vec<(usize, usize)> {
+/// Min-priority queue of search nodes. Dijkstra's algorithm only ever pushes nodes that cost at
+/// least as much as the last one popped, which the bucket and radix queues rely on.
+trait PriorityQueue {
+    fn push(&mut self, node: Node);
+    fn pop(&mut self) -> Option<Node>;
+}
+
+impl PriorityQueue for BinaryHeap<Node> {
+    fn push(&mut self, node: Node) {
+        BinaryHeap::push(self, node);
+    }
+
+    fn pop(&mut self) -> Option<Node> {
+        BinaryHeap::pop(self)
+    }
+}
+
+/// Dial's algorithm: one bucket per total cost, scanned in increasing order. Costs on the risk
+/// map grow slowly and in small steps, so buckets are dense and pushing and popping are `O(1)`.
+#[derive(Default)]
+struct BucketQueue {
+    buckets: Vec<Vec<Position>>,
+    current: usize,
+    len: usize,
+}
+
+impl PriorityQueue for BucketQueue {
+    fn push(&mut self, node: Node) {
+        let cost = node.cost as usize;
+        if cost >= self.buckets.len() {
+            self.buckets.resize_with(cost + 1, Vec::new);
+        }
+
+        self.buckets[cost].push(node.position);
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> Option<Node> {
+        if self.len == 0 {
+            return None;
+        }
+
+        loop {
+            if let Some(position) = self.buckets[self.current].pop() {
+                self.len -= 1;
+                return Some(Node {
+                    position,
+                    cost: self.current as u32,
+                });
+            }
+
+            // release the memory of buckets that can never be used again
+            self.buckets[self.current] = Vec::new();
+            self.current += 1;
+        }
+    }
+}
+
+/// Radix heap: node costs are bucketed by the highest bit they differ from the last popped cost
+/// in, so each node moves down at most 32 buckets over its lifetime.
+struct RadixHeap {
+    buckets: [Vec<Node>; 33],
+    last: u32,
+    len: usize,
+}
+
+impl Default for RadixHeap {
+    fn default() -> Self {
+        Self {
+            buckets: std::array::from_fn(|_| Vec::new()),
+            last: 0,
+            len: 0,
+        }
+    }
+}
+
+impl RadixHeap {
+    fn bucket(&self, cost: u32) -> usize {
+        (u32::BITS - (cost ^ self.last).leading_zeros()) as usize
+    }
+}
+
+impl PriorityQueue for RadixHeap {
+    fn push(&mut self, node: Node) {
+        let bucket = self.bucket(node.cost);
+        self.buckets[bucket].push(node);
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> Option<Node> {
+        if self.len == 0 {
+            return None;
+        }
+
+        if self.buckets[0].is_empty() {
+            let bucket = self
+                .buckets
+                .iter()
+                .position(|bucket| !bucket.is_empty())
+                .expect("A non-empty heap has a non-empty bucket");
+            let nodes = std::mem::take(&mut self.buckets[bucket]);
+            self.last = nodes
+                .iter()
+                .map(|node| node.cost)
+                .min()
+                .expect("Bucket is not empty");
+            for node in nodes {
+                let bucket = self.bucket(node.cost);
+                self.buckets[bucket].push(node);
+            }
+        }
+
+        self.len -= 1;
+        self.buckets[0].pop()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum QueueKind {
+    Binary,
+    Bucket,
+    Radix,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Movement {
+    /// Up, down, left and right
+    Four,
+    /// Diagonals as well
+    Eight,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum CostModel {
+    /// Moving into a cell costs its risk level
+    Entry,
+    /// Moving between two cells costs a function of the risk levels they are moving from and to
+    Edge(fn(u32, u32) -> u32),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Query {
+    pub start: Position,
+    pub goal: Position,
+    pub movement: Movement,
+    pub cost_model: CostModel,
+    pub queue: QueueKind,
+}
+
+impl Query {
+    /// Moving in four directions and paying the risk of every cell entered
+    pub fn new(start: Position, goal: Position) -> Self {
+        Self {
+            start,
+            goal,
+            movement: Movement::Four,
+            cost_model: CostModel::Entry,
+            queue: QueueKind::Binary,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Route {
+    pub cost: u32,
+    /// Every cell visited, from the start to the goal inclusive
+    pub path: Vec<Position>,
+}
+
+fn adjacencies(
+    position: Position,
+    height: usize,
+    width: usize,
+    movement: Movement,
+) -> Vec<Position> {
     let mut adjacencies = Vec::new();
 
     // up
@@ -37,7 +319,7 @@ fn adjacencies(position: (usize, usize), height: usize, width: usize) -> Vec<(us
     }
 
     // down
-    if position.0 < height - 1 {
+    if position.0 + 1 < height {
         adjacencies.push((position.0 + 1, position.1));
     }
 
@@ -47,100 +329,731 @@ fn adjacencies(position: (usize, usize), height: usize, width: usize) -> Vec<(us
     }
 
     // right
-    if position.1 < width - 1 {
+    if position.1 + 1 < width {
         adjacencies.push((position.0, position.1 + 1));
     }
 
+    if movement == Movement::Eight {
+        for (delta_row, delta_column) in [(-1, -1), (-1, 1), (1, -1), (1, 1)] {
+            let row = position.0 as i64 + delta_row;
+            let column = position.1 as i64 + delta_column;
+            if row >= 0 && column >= 0 && (row as usize) < height && (column as usize) < width {
+                adjacencies.push((row as usize, column as usize));
+            }
+        }
+    }
+
     adjacencies
 }
 
-fn part_1(input: &[Vec<u32>]) -> u32 {
-    let height = input.len();
-    let width = input[0].len();
+/// Direction from one cell to an adjacent one, as a non-zero code that fits in a byte
+fn direction(from: Position, to: Position) -> u8 {
+    let delta_row = (to.0 as i64 - from.0 as i64 + 1) as u8;
+    let delta_column = (to.1 as i64 - from.1 as i64 + 1) as u8;
+    delta_row * 3 + delta_column + 1
+}
+
+/// Marks a cell a search started from, which was not reached from anywhere
+const SOURCE: u8 = u8::MAX;
+
+/// The cell that moving in `direction` reached `to` from
+fn step_back(to: Position, direction: u8) -> Position {
+    let code = direction - 1;
+    (
+        (to.0 as i64 - (code / 3) as i64 + 1) as usize,
+        (to.1 as i64 - (code % 3) as i64 + 1) as usize,
+    )
+}
+
+/// Cost of moving from one cell to an adjacent one, or `None` if either is a wall
+fn step_cost(map: &impl Grid, cost_model: CostModel, from: Position, to: Position) -> Option<u32> {
+    let to_risk = map.risk(to)?;
+    match cost_model {
+        CostModel::Entry => Some(to_risk),
+        CostModel::Edge(cost) => Some(cost(map.risk(from)?, to_risk)),
+    }
+}
+
+fn path_cost(map: &impl Grid, cost_model: CostModel, path: &[Position]) -> Option<u32> {
+    path.windows(2)
+        .map(|step| step_cost(map, cost_model, step[0], step[1]))
+        .sum()
+}
+
+/// Finds the cheapest route for `query` with Dijkstra's algorithm, or `None` if the goal cannot
+/// be reached. The start and goal must both be passable cells on the map.
+fn shortest_path(map: &impl Grid, query: &Query) -> Option<Route> {
+    map.risk(query.goal)?;
+    let goal = query.goal;
+    search(
+        map,
+        query,
+        &[query.start],
+        &|position| position == goal,
+        &|_, _| false,
+    )
+}
+
+/// Finds the cheapest route from the start of `query` to any cell in a target region. The goal
+/// of `query` is ignored.
+fn shortest_path_to_region(
+    map: &impl Grid,
+    query: &Query,
+    region: &dyn Fn(Position) -> bool,
+) -> Option<Route> {
+    search(map, query, &[query.start], region, &|_, _| false)
+}
+
+/// Runs Dijkstra's algorithm from every cell in `sources` at once until a cell matching
+/// `is_target` is settled, never taking a step that `blocked` rejects, using the queue chosen
+/// by `query`.
+fn search(
+    map: &impl Grid,
+    query: &Query,
+    sources: &[Position],
+    is_target: &dyn Fn(Position) -> bool,
+    blocked: &dyn Fn(Position, Position) -> bool,
+) -> Option<Route> {
+    match query.queue {
+        QueueKind::Binary => {
+            search_with(map, query, sources, is_target, blocked, BinaryHeap::new())
+        }
+        QueueKind::Bucket => search_with(
+            map,
+            query,
+            sources,
+            is_target,
+            blocked,
+            BucketQueue::default(),
+        ),
+        QueueKind::Radix => search_with(
+            map,
+            query,
+            sources,
+            is_target,
+            blocked,
+            RadixHeap::default(),
+        ),
+    }
+}
+
+/// Only one byte is kept per cell, recording the direction each settled cell was reached from.
+/// Tentative costs are only kept for cells on the frontier, which stays small on a grid.
+fn search_with(
+    map: &impl Grid,
+    query: &Query,
+    sources: &[Position],
+    is_target: &dyn Fn(Position) -> bool,
+    blocked: &dyn Fn(Position, Position) -> bool,
+    mut path: impl PriorityQueue,
+) -> Option<Route> {
+    let (height, width) = (map.height(), map.width());
+    let index = |(row, column): Position| row * width + column;
+    let mut settled_from = vec![0u8; height * width];
+    let mut frontier_costs = HashMap::new();
+    for &source in sources {
+        if map.risk(source).is_some() {
+            frontier_costs.insert(index(source), (0, SOURCE));
+            path.push(Node {
+                position: source,
+                cost: 0,
+            });
+        }
+    }
+
+    while let Some(node) = path.pop() {
+        // skip stale entries for cells that were already reached more cheaply
+        let from = match frontier_costs.get(&index(node.position)) {
+            Some(&(cost, from)) if cost == node.cost => from,
+            _ => continue,
+        };
+        frontier_costs.remove(&index(node.position));
+        settled_from[index(node.position)] = from;
+
+        if is_target(node.position) {
+            let mut route = vec![node.position];
+            let mut current = node.position;
+            while settled_from[index(current)] != SOURCE {
+                current = step_back(current, settled_from[index(current)]);
+                route.push(current);
+            }
+            route.reverse();
+
+            return Some(Route {
+                cost: node.cost,
+                path: route,
+            });
+        }
+
+        for adjacent in adjacencies(node.position, height, width, query.movement) {
+            if settled_from[index(adjacent)] != 0 || blocked(node.position, adjacent) {
+                continue;
+            }
+
+            let cost = match step_cost(map, query.cost_model, node.position, adjacent) {
+                Some(step_cost) => node.cost + step_cost,
+                None => continue,
+            };
+            let best = frontier_costs
+                .get(&index(adjacent))
+                .map_or(u32::MAX, |&(cost, _)| cost);
+            if cost < best {
+                frontier_costs.insert(index(adjacent), (cost, direction(node.position, adjacent)));
+                path.push(Node {
+                    position: adjacent,
+                    cost,
+                });
+            }
+        }
+    }
+
+    None
+}
+
+/// Cost of the cheapest route from the start of `query` to every cell, in row-major order, or
+/// `None` for cells that cannot be reached. The goal of `query` is ignored.
+fn distances(map: &impl Grid, query: &Query) -> Vec<Option<u32>> {
+    let (height, width) = (map.height(), map.width());
+    let index = |(row, column): Position| row * width + column;
+    let mut path_costs = vec![None; height * width];
+    if map.risk(query.start).is_none() {
+        return path_costs;
+    }
+
     let mut path = BinaryHeap::new();
+    path_costs[index(query.start)] = Some(0);
     path.push(Node {
-        position: (0, 1),
-        cost: input[0][1],
-    });
-    path.push(Node {
-        position: (1, 0),
-        cost: input[1][0],
+        position: query.start,
+        cost: 0,
     });
-    let mut path_costs = HashMap::new();
-    path_costs.insert((0, 0), 0);
-
     while let Some(node) = path.pop() {
-        // if we're at the bottom right corner, we found the destination
-        if node.position == (height - 1, width - 1) {
-            return node.cost;
+        if path_costs[index(node.position)].is_some_and(|cost| cost < node.cost) {
+            continue;
         }
 
-        if let Some(cost) = path_costs.get(&node.position) {
-            if *cost <= node.cost {
-                continue;
+        for adjacent in adjacencies(node.position, height, width, query.movement) {
+            if let Some(step_cost) = step_cost(map, query.cost_model, node.position, adjacent) {
+                let cost = node.cost + step_cost;
+                if path_costs[index(adjacent)].is_none_or(|best| cost < best) {
+                    path_costs[index(adjacent)] = Some(cost);
+                    path.push(Node {
+                        position: adjacent,
+                        cost,
+                    });
+                }
             }
         }
+    }
 
-        for adjacent in adjacencies(node.position, height, width) {
-            let cost = node.cost + input[adjacent.0][adjacent.1];
-            if let Some(cost_for_adjacent) = path_costs.get(&adjacent) {
-                if *cost_for_adjacent <= cost {
-                    continue;
+    path_costs
+}
+
+/// Finds the same route as [`shortest_path`], but searches forwards from the start and
+/// backwards from the goal at the same time, always growing whichever frontier is cheaper. Once
+/// the two cheapest frontier costs add up to at least the best route seen joining the two
+/// searches, nothing cheaper can be found.
+fn bidirectional_shortest_path(map: &impl Grid, query: &Query) -> Option<Route> {
+    let (height, width) = (map.height(), map.width());
+    map.risk(query.start)?;
+    map.risk(query.goal)?;
+
+    let index = |(row, column): Position| row * width + column;
+    // Index 0 searches forwards from the start, index 1 backwards from the goal
+    let mut path_costs = [
+        vec![u32::MAX; height * width],
+        vec![u32::MAX; height * width],
+    ];
+    let mut reached_from = [vec![0u8; height * width], vec![0u8; height * width]];
+    let mut settled = [vec![false; height * width], vec![false; height * width]];
+    let mut paths = [BinaryHeap::new(), BinaryHeap::new()];
+    for (side, source) in [query.start, query.goal].into_iter().enumerate() {
+        path_costs[side][index(source)] = 0;
+        reached_from[side][index(source)] = SOURCE;
+        paths[side].push(Node {
+            position: source,
+            cost: 0,
+        });
+    }
+
+    let mut best: Option<(u32, Position)> = None;
+    loop {
+        let tops = [
+            paths[0].peek().map(|node| node.cost),
+            paths[1].peek().map(|node| node.cost),
+        ];
+        let side = match tops {
+            [Some(forward), Some(backward)] => {
+                if best.is_some_and(|(cost, _)| forward + backward >= cost) {
+                    break;
+                }
+                if forward <= backward {
+                    0
+                } else {
+                    1
                 }
             }
+            _ => break,
+        };
 
-            path.push(Node {
-                position: adjacent,
-                cost,
-            });
+        let node = paths[side].pop().expect("Frontier is not empty");
+        if settled[side][index(node.position)] {
+            continue;
+        }
+        settled[side][index(node.position)] = true;
 
-            path_costs.insert(node.position, node.cost);
+        for adjacent in adjacencies(node.position, height, width, query.movement) {
+            // Searching backwards, stepping to `adjacent` means the route moves from it to here
+            let step_cost = if side == 0 {
+                step_cost(map, query.cost_model, node.position, adjacent)
+            } else {
+                step_cost(map, query.cost_model, adjacent, node.position)
+            };
+            let cost = match step_cost {
+                Some(step_cost) => node.cost + step_cost,
+                None => continue,
+            };
+
+            if cost < path_costs[side][index(adjacent)] {
+                path_costs[side][index(adjacent)] = cost;
+                reached_from[side][index(adjacent)] = direction(node.position, adjacent);
+                paths[side].push(Node {
+                    position: adjacent,
+                    cost,
+                });
+            }
+
+            let other = path_costs[1 - side][index(adjacent)];
+            if other != u32::MAX {
+                let total = path_costs[side][index(adjacent)] + other;
+                if best.is_none_or(|(cost, _)| total < cost) {
+                    best = Some((total, adjacent));
+                }
+            }
+        }
+
+        // The sources themselves can also be where the searches meet
+        let other = path_costs[1 - side][index(node.position)];
+        if other != u32::MAX && best.is_none_or(|(cost, _)| node.cost + other < cost) {
+            best = Some((node.cost + other, node.position));
         }
     }
 
-    unreachable!()
+    let (cost, meeting) = best?;
+    let mut route = Vec::new();
+    let mut current = meeting;
+    while reached_from[0][index(current)] != SOURCE {
+        current = step_back(current, reached_from[0][index(current)]);
+        route.push(current);
+    }
+    route.reverse();
+    route.push(meeting);
+    let mut current = meeting;
+    while reached_from[1][index(current)] != SOURCE {
+        current = step_back(current, reached_from[1][index(current)]);
+        route.push(current);
+    }
+
+    Some(Route { cost, path: route })
 }
 
-fn build_part_2_map(input: &[Vec<u32>]) -> Vec<Vec<u32>> {
-    let tile_height = input.len();
-    let tile_width = input[0].len();
+/// Finds up to `k` cheapest routes for `query` that differ in at least one step, cheapest
+/// first, using Yen's algorithm: each new route leaves an earlier one at some cell, and is the
+/// cheapest way to do that without repeating an earlier route or revisiting its own cells.
+fn k_shortest_paths(map: &impl Grid, query: &Query, k: usize) -> Vec<Route> {
+    let mut routes: Vec<Route> = Vec::new();
+    let mut candidates: Vec<Route> = Vec::new();
+    match shortest_path(map, query) {
+        Some(route) => routes.push(route),
+        None => return routes,
+    }
 
-    let mut new_map = vec![vec![0; 5 * tile_width]; 5 * tile_height];
+    while routes.len() < k {
+        let previous = routes
+            .last()
+            .expect("At least one route was found")
+            .path
+            .clone();
+        for spur in 0..previous.len() - 1 {
+            let root = &previous[..=spur];
+            let blocked_steps: HashSet<(Position, Position)> = routes
+                .iter()
+                .filter(|route| route.path.len() > spur + 1 && route.path[..=spur] == *root)
+                .map(|route| (route.path[spur], route.path[spur + 1]))
+                .collect();
+            let root_cells: HashSet<Position> = root[..spur].iter().copied().collect();
+            let goal = query.goal;
 
-    for tile_row in 0..5 {
-        for tile_column in 0..5 {
-            for i in 0..tile_height {
-                for j in 0..tile_width {
-                    let mut new_risk_value = input[i][j] + tile_row + tile_column;
-                    // values > 9 wrap back to 1
-                    if new_risk_value > 9 {
-                        new_risk_value -= 9;
-                    }
-                    new_map[tile_row as usize * tile_height + i]
-                        [tile_column as usize * tile_width + j] = new_risk_value;
+            let spur_route = search(
+                map,
+                query,
+                &[previous[spur]],
+                &|position| position == goal,
+                &|from, to| root_cells.contains(&to) || blocked_steps.contains(&(from, to)),
+            );
+            if let Some(spur_route) = spur_route {
+                let mut path = root[..spur].to_vec();
+                path.extend(spur_route.path);
+                let is_new = !routes
+                    .iter()
+                    .chain(candidates.iter())
+                    .any(|route| route.path == path);
+                if is_new {
+                    let cost = path_cost(map, query.cost_model, &path)
+                        .expect("Every cell on a found route is passable");
+                    candidates.push(Route { cost, path });
                 }
             }
         }
+
+        match candidates
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, route)| route.cost)
+            .map(|(i, _)| i)
+        {
+            Some(cheapest) => routes.push(candidates.swap_remove(cheapest)),
+            None => break,
+        }
     }
 
-    new_map
+    routes
+}
+
+fn corner_to_corner(map: &impl Grid) -> Query {
+    Query::new(
+        (0, 0),
+        (
+            map.height().saturating_sub(1),
+            map.width().saturating_sub(1),
+        ),
+    )
+}
+
+fn part_1(input: &impl Grid) -> u32 {
+    shortest_path(input, &corner_to_corner(input))
+        .expect("No route to the bottom right corner")
+        .cost
 }
 
-fn part_2(input: &[Vec<u32>]) -> u32 {
-    let new_map = build_part_2_map(input);
-    part_1(&new_map)
+fn part_2(input: &RiskMap) -> Result<u32, MapError> {
+    Ok(part_1(&TiledMap::new(input, 5)?))
+}
+
+/// The example map from the puzzle
+const EXAMPLE: [&str; 10] = [
+    "1163751742",
+    "1381373672",
+    "2136511328",
+    "3694931569",
+    "7463417111",
+    "1319128137",
+    "1359912421",
+    "3125421639",
+    "1293138521",
+    "2311944581",
+];
+
+/// Reads a risk map, where any character other than a digit is a wall that cannot be entered
+fn parse(lines: &[impl AsRef<str>]) -> Result<RiskMap, MapError> {
+    lines
+        .iter()
+        .enumerate()
+        .map(|(row, line)| {
+            line.as_ref()
+                .chars()
+                .enumerate()
+                .map(|(column, value)| match value {
+                    '0' => Err(MapError::ZeroRisk((row, column))),
+                    _ => Ok(value.to_digit(10)),
+                })
+                .collect()
+        })
+        .collect()
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let input: Vec<Vec<u32>> = input_parser::parse("puzzle15")
+    let input = parse(&input_parser::parse("puzzle15"))?;
+    println!("Part 1: {}", part_1(&input));
+    println!("Part 2: {}", part_2(&input)?);
+
+    if std::env::args().any(|arg| arg == "--bench") {
+        let tiled = TiledMap::new(&input, 20)?;
+        for queue in [QueueKind::Binary, QueueKind::Bucket, QueueKind::Radix] {
+            let mut query = corner_to_corner(&tiled);
+            query.queue = queue;
+            let start = Instant::now();
+            let route = shortest_path(&tiled, &query).expect("No route across the tiled map");
+            println!(
+                "{}x{} tiled map with {:?} queue: {} in {:?}",
+                tiled.width(),
+                tiled.height(),
+                queue,
+                route.cost,
+                start.elapsed()
+            );
+        }
+    }
+
+    let reversed = TiledMap::with_wrapping(&input, 5, 8, 9)?;
+    println!(
+        "5x5 tiles with risk falling by 1 per tile: {}",
+        part_1(&reversed)
+    );
+
+    let mut query = corner_to_corner(&input);
+    query.movement = Movement::Eight;
+    query.cost_model = CostModel::Edge(|from, to| from.abs_diff(to) + 1);
+    if let Some(route) = shortest_path(&input, &query) {
+        println!(
+            "Diagonal route paying for changes in risk: cost {} over {} cells",
+            route.cost,
+            route.path.len()
+        );
+    }
+
+    let query = corner_to_corner(&input);
+    let bidirectional = bidirectional_shortest_path(&input, &query).expect("No route");
+    println!(
+        "Bidirectional search: {} over {} cells",
+        bidirectional.cost,
+        bidirectional.path.len()
+    );
+
+    let example = parse(&EXAMPLE)?;
+    let costs: Vec<u32> = k_shortest_paths(&example, &corner_to_corner(&example), 3)
         .iter()
-        .map(|line| {
-            line.chars()
-                .map(|value| value.to_digit(10).expect("Failed to parse digit"))
+        .map(|route| route.cost)
+        .collect();
+    println!(
+        "Three cheapest distinct routes across the example: {:?}",
+        costs
+    );
+
+    let distance_map = distances(&input, &query);
+    let furthest = distance_map.iter().flatten().max().expect("Map is empty");
+    println!("Costliest cell to reach from the top left: {}", furthest);
+
+    let (height, width) = (input.height(), input.width());
+    let region = |(row, column): Position| row >= height / 2 && column >= width / 2;
+    if let Some(route) = shortest_path_to_region(&input, &query, &region) {
+        println!(
+            "Cheapest way into the bottom right quarter: {} ending at {:?}",
+            route.cost,
+            route.path.last().expect("Routes are never empty")
+        );
+    }
+
+    let walled: RiskMap = input
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|&risk| risk.filter(|&risk| risk < 9))
                 .collect()
         })
         .collect();
-    println!("Part 1: {}", part_1(&input));
-    println!("Part 1: {}", part_2(&input));
+    let query = Query::new((0, 0), (input.len() / 2, input[0].len() / 2));
+    match shortest_path(&walled, &query) {
+        Some(route) => println!(
+            "Route to the centre avoiding every 9: cost {} via {:?}",
+            route.cost,
+            &route.path[..route.path.len().min(5)]
+        ),
+        None => println!("The centre cannot be reached without crossing a 9"),
+    }
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Small xorshift generator, so the searches can be checked against many maps
+    struct Random(u64);
+
+    impl Random {
+        fn next(&mut self, bound: u64) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 % bound
+        }
+
+        /// A map of up to `max_height` by `max_width` cells, with risks from 1 to 9 and roughly
+        /// one wall in every `walls` cells
+        fn map(&mut self, max_height: u64, max_width: u64, walls: u64) -> RiskMap {
+            let height = 1 + self.next(max_height);
+            let width = 1 + self.next(max_width);
+            (0..height)
+                .map(|_| {
+                    (0..width)
+                        .map(|_| (self.next(walls) != 0).then(|| 1 + self.next(9) as u32))
+                        .collect()
+                })
+                .collect()
+        }
+    }
+
+    fn queries(map: &RiskMap) -> Vec<Query> {
+        let mut queries = Vec::new();
+        for movement in [Movement::Four, Movement::Eight] {
+            for cost_model in [
+                CostModel::Entry,
+                CostModel::Edge(|from, to| from.abs_diff(to) + 1),
+            ] {
+                let mut query = corner_to_corner(map);
+                query.movement = movement;
+                query.cost_model = cost_model;
+                queries.push(query);
+            }
+        }
+        queries
+    }
+
+    /// Costs of every route from the start to the goal that never visits a cell twice, cheapest
+    /// first
+    fn simple_route_costs(map: &RiskMap, query: &Query) -> Vec<u32> {
+        fn extend(
+            map: &RiskMap,
+            query: &Query,
+            path: &mut Vec<Position>,
+            cost: u32,
+            costs: &mut Vec<u32>,
+        ) {
+            let position = *path.last().expect("Paths start at the start");
+            if position == query.goal {
+                costs.push(cost);
+                return;
+            }
+            for adjacent in adjacencies(position, map.height(), map.width(), query.movement) {
+                if path.contains(&adjacent) {
+                    continue;
+                }
+                if let Some(step_cost) = step_cost(map, query.cost_model, position, adjacent) {
+                    path.push(adjacent);
+                    extend(map, query, path, cost + step_cost, costs);
+                    path.pop();
+                }
+            }
+        }
+
+        let mut costs = Vec::new();
+        if map.risk(query.start).is_some() {
+            extend(map, query, &mut vec![query.start], 0, &mut costs);
+        }
+        costs.sort_unstable();
+        costs
+    }
+
+    #[test]
+    fn example_routes() {
+        let example = parse(&EXAMPLE).expect("Example is valid");
+        assert_eq!(part_1(&example), 40);
+        assert_eq!(part_2(&example).expect("Example has no zero risks"), 315);
+    }
+
+    #[test]
+    fn zero_risks_and_moduli_are_rejected() {
+        assert!(matches!(
+            parse(&["19", "30"]),
+            Err(MapError::ZeroRisk((1, 1)))
+        ));
+
+        let tile = vec![vec![Some(1), None], vec![Some(4), Some(0)]];
+        assert!(matches!(
+            TiledMap::new(&tile, 2),
+            Err(MapError::ZeroRisk((1, 1)))
+        ));
+        let tile = vec![vec![Some(1), Some(2)]];
+        assert!(matches!(
+            TiledMap::with_wrapping(&tile, 2, 1, 0),
+            Err(MapError::ZeroModulus)
+        ));
+        let tiled = TiledMap::with_wrapping(&tile, 2, 1, 2).expect("Tile is valid");
+        assert_eq!(tiled.risk((0, 3)), Some(1));
+    }
+
+    #[test]
+    fn distances_match_dijkstra() {
+        let mut random = Random(0x9E37_79B9_7F4A_7C15);
+        for _ in 0..50 {
+            let map = random.map(6, 6, 5);
+            for query in queries(&map) {
+                let distances = distances(&map, &query);
+                for row in 0..map.height() {
+                    for column in 0..map.width() {
+                        let query = Query {
+                            goal: (row, column),
+                            ..query
+                        };
+                        assert_eq!(
+                            distances[row * map.width() + column],
+                            shortest_path(&map, &query).map(|route| route.cost),
+                            "{:?} on {:?}",
+                            query,
+                            map
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bidirectional_search_matches_dijkstra() {
+        let mut random = Random(0x2545_F491_4F6C_DD1D);
+        for _ in 0..200 {
+            let map = random.map(8, 8, 5);
+            for query in queries(&map) {
+                let expected = shortest_path(&map, &query).map(|route| route.cost);
+                let route = bidirectional_shortest_path(&map, &query);
+                assert_eq!(
+                    route.as_ref().map(|route| route.cost),
+                    expected,
+                    "{:?} on {:?}",
+                    query,
+                    map
+                );
+                if let Some(route) = route {
+                    assert_eq!(route.path.first(), Some(&query.start));
+                    assert_eq!(route.path.last(), Some(&query.goal));
+                    assert_eq!(
+                        path_cost(&map, query.cost_model, &route.path),
+                        Some(route.cost)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn k_shortest_paths_are_the_cheapest_simple_routes() {
+        let mut random = Random(0xD1B5_4A32_D192_ED03);
+        for _ in 0..50 {
+            let map = random.map(3, 4, 6);
+            for query in queries(&map) {
+                let expected = simple_route_costs(&map, &query);
+                let routes = k_shortest_paths(&map, &query, 6);
+                let costs: Vec<u32> = routes.iter().map(|route| route.cost).collect();
+                assert_eq!(
+                    costs,
+                    expected[..expected.len().min(6)],
+                    "{:?} on {:?}",
+                    query,
+                    map
+                );
+                assert_eq!(
+                    costs.first().copied(),
+                    shortest_path(&map, &query).map(|route| route.cost)
+                );
+                for (i, route) in routes.iter().enumerate() {
+                    assert_eq!(
+                        path_cost(&map, query.cost_model, &route.path),
+                        Some(route.cost)
+                    );
+                    assert!(routes[..i].iter().all(|other| other.path != route.path));
+                }
+            }
+        }
+    }
+}
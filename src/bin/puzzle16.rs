@@ -1,150 +1,1122 @@
-use std::error::Error;
-use utils::input_parser;
+use std::{error::Error, fmt::Display, iter::Peekable, str::Chars, str::FromStr};
+use utils::{big_uint::BigUint, input_parser};
 
-fn numeric_value(c: &[char]) -> usize {
-    let c: String = c.iter().collect();
-    usize::from_str_radix(&c, 2).unwrap()
+/// Deepest nesting of packets a transmission may have. Decoding, evaluating and printing all
+/// recurse through sub-packets, so this keeps a crafted transmission from overflowing the stack.
+const MAX_DEPTH: usize = 1_000;
+
+/// Reading past the end of a transmission
+#[derive(Debug)]
+struct OutOfBits {
+    offset: usize,
+    wanted: usize,
+    available: usize,
 }
 
-enum Operator {
-    Sum(usize),
-    Product(usize),
-    Min(usize),
-    Max(usize),
-    Gt(Vec<usize>),
-    Lt(Vec<usize>),
-    Eq(Vec<usize>),
+impl Display for OutOfBits {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Wanted {} bits at bit {} but only {} remain",
+            self.wanted, self.offset, self.available
+        )
+    }
 }
 
-impl Operator {
-    fn insert(&mut self, val: usize) {
-        match self {
-            Operator::Sum(state) => {
-                *state += val;
+impl Error for OutOfBits {}
+
+/// Reads big-endian bit fields straight out of a borrowed byte buffer
+#[derive(Clone, Copy)]
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    /// Offset of the next bit to read, from the start of `bytes`
+    position: usize,
+    /// Number of meaningful bits in `bytes`, which can end part way through the last byte
+    len: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(bytes: &'a [u8], len: usize) -> Self {
+        assert!(
+            len <= bytes.len() * 8,
+            "Length runs past the end of the buffer"
+        );
+        Self {
+            bytes,
+            position: 0,
+            len,
+        }
+    }
+
+    fn position(&self) -> usize {
+        self.position
+    }
+
+    fn remaining(&self) -> usize {
+        self.len - self.position
+    }
+
+    /// Reads the next `n` bits, most significant first, where `n` is at most 64
+    fn read_bits(&mut self, n: usize) -> Result<u64, OutOfBits> {
+        assert!(n <= 64, "Cannot read more than 64 bits at once");
+        if n > self.remaining() {
+            return Err(OutOfBits {
+                offset: self.position,
+                wanted: n,
+                available: self.remaining(),
+            });
+        }
+
+        let mut value = 0u64;
+        let mut wanted = n;
+        while wanted > 0 {
+            let byte = self.bytes[self.position / 8];
+            let used = self.position % 8;
+            let taken = wanted.min(8 - used);
+            let bits = (byte >> (8 - used - taken)) & (u8::MAX >> (8 - taken));
+            value = (value << taken) | bits as u64;
+            self.position += taken;
+            wanted -= taken;
+        }
+
+        Ok(value)
+    }
+
+    fn read_bit(&mut self) -> Result<bool, OutOfBits> {
+        Ok(self.read_bits(1)? == 1)
+    }
+
+    /// Whether every bit left to read is zero, without reading any of them
+    fn rest_is_zero(&self) -> bool {
+        let mut rest = *self;
+        while rest.remaining() > 0 {
+            let n = rest.remaining().min(64);
+            if rest.read_bits(n).expect("Only remaining bits are read") != 0 {
+                return false;
             }
-            Operator::Product(state) => {
-                *state *= val;
+        }
+        true
+    }
+}
+
+/// Packs hex digits two to a byte, returning the bytes and how many bits they hold
+fn hex_to_bytes(input: &str) -> Result<(Vec<u8>, usize), DecodeError> {
+    let mut bytes = Vec::with_capacity(input.len() / 2);
+    let mut len = 0;
+    for (position, digit) in input.trim().chars().enumerate() {
+        let nibble = digit
+            .to_digit(16)
+            .ok_or(DecodeError::InvalidHexDigit { position, digit })? as u8;
+        if position % 2 == 0 {
+            bytes.push(nibble << 4);
+        } else {
+            *bytes.last_mut().expect("A byte was started") |= nibble;
+        }
+        len += 4;
+    }
+
+    Ok((bytes, len))
+}
+
+/// Appends big-endian bit fields to a growing byte buffer
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    /// Number of bits written so far
+    len: usize,
+}
+
+impl BitWriter {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn write_bit(&mut self, bit: bool) {
+        if self.len.is_multiple_of(8) {
+            self.bytes.push(0);
+        }
+        if bit {
+            self.bytes[self.len / 8] |= 0x80 >> (self.len % 8);
+        }
+        self.len += 1;
+    }
+
+    /// Writes the low `n` bits of `value`, most significant first
+    fn write_bits(&mut self, value: u64, n: usize) {
+        for bit in (0..n).rev() {
+            self.write_bit((value >> bit) & 1 == 1);
+        }
+    }
+
+    /// Overwrites `n` bits already written, starting at bit `offset`
+    fn set_bits(&mut self, offset: usize, value: u64, n: usize) {
+        assert!(
+            offset + n <= self.len,
+            "Can only overwrite bits already written"
+        );
+        for i in 0..n {
+            let position = offset + i;
+            let mask = 0x80 >> (position % 8);
+            if (value >> (n - 1 - i)) & 1 == 1 {
+                self.bytes[position / 8] |= mask;
+            } else {
+                self.bytes[position / 8] &= !mask;
             }
-            Operator::Min(state) => {
-                *state = (*state).min(val);
+        }
+    }
+
+    /// The bits written so far as hex digits, padding the last digit with zero bits
+    fn to_hex(&self) -> String {
+        (0..self.len.div_ceil(4))
+            .map(|nibble| {
+                let byte = self.bytes[nibble / 2];
+                let digit = if nibble % 2 == 0 {
+                    byte >> 4
+                } else {
+                    byte & 0xf
+                };
+                char::from_digit(digit as u32, 16)
+                    .expect("Nibbles are below 16")
+                    .to_ascii_uppercase()
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OperatorKind {
+    Sum,
+    Product,
+    Min,
+    Max,
+    Gt,
+    Lt,
+    Eq,
+}
+
+impl OperatorKind {
+    fn from_type_id(type_id: usize) -> Option<Self> {
+        match type_id {
+            0 => Some(OperatorKind::Sum),
+            1 => Some(OperatorKind::Product),
+            2 => Some(OperatorKind::Min),
+            3 => Some(OperatorKind::Max),
+            5 => Some(OperatorKind::Gt),
+            6 => Some(OperatorKind::Lt),
+            7 => Some(OperatorKind::Eq),
+            _ => None,
+        }
+    }
+
+    /// Whether an operator of this kind can take `count` operands
+    fn accepts(&self, count: usize) -> bool {
+        match self {
+            OperatorKind::Gt | OperatorKind::Lt | OperatorKind::Eq => count == 2,
+            _ => count > 0,
+        }
+    }
+
+    fn type_id(&self) -> usize {
+        match self {
+            OperatorKind::Sum => 0,
+            OperatorKind::Product => 1,
+            OperatorKind::Min => 2,
+            OperatorKind::Max => 3,
+            OperatorKind::Gt => 5,
+            OperatorKind::Lt => 6,
+            OperatorKind::Eq => 7,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            OperatorKind::Sum => "sum",
+            OperatorKind::Product => "product",
+            OperatorKind::Min => "min",
+            OperatorKind::Max => "max",
+            OperatorKind::Gt => "gt",
+            OperatorKind::Lt => "lt",
+            OperatorKind::Eq => "eq",
+        }
+    }
+}
+
+/// How an operator packet says where its sub-packets end
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LengthMode {
+    /// Length type 0: the total number of bits taken by the sub-packets
+    TotalBits,
+    /// Length type 1: the number of immediate sub-packets
+    PacketCount,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Packet {
+    Literal {
+        version: u8,
+        value: BigUint,
+    },
+    Operator {
+        version: u8,
+        kind: OperatorKind,
+        length_mode: LengthMode,
+        children: Vec<Packet>,
+    },
+}
+
+impl Packet {
+    fn version_sum(&self) -> usize {
+        match self {
+            Packet::Literal { version, .. } => *version as usize,
+            Packet::Operator {
+                version, children, ..
+            } => *version as usize + children.iter().map(Packet::version_sum).sum::<usize>(),
+        }
+    }
+
+    /// Evaluates the packet, falling back to arbitrary precision only if some value along the
+    /// way does not fit in a u128
+    fn evaluate(&self) -> Result<BigUint, OperandCountError> {
+        self.check_operands()?;
+        Ok(match self.checked_evaluate() {
+            Some(value) => BigUint::from(value),
+            None => self.big_evaluate(),
+        })
+    }
+
+    /// Checks every operator in the tree has a number of operands it can take, which decoded
+    /// packets always do but packets built by hand might not
+    fn check_operands(&self) -> Result<(), OperandCountError> {
+        match self {
+            Packet::Literal { .. } => Ok(()),
+            Packet::Operator { kind, children, .. } => {
+                if !kind.accepts(children.len()) {
+                    return Err(OperandCountError {
+                        kind: *kind,
+                        count: children.len(),
+                    });
+                }
+                children.iter().try_for_each(Packet::check_operands)
             }
-            Operator::Max(state) => {
-                *state = (*state).max(val);
+        }
+    }
+
+    fn checked_evaluate(&self) -> Option<u128> {
+        let (kind, children) = match self {
+            Packet::Literal { value, .. } => return value.to_u128(),
+            Packet::Operator { kind, children, .. } => (kind, children),
+        };
+
+        let values = children
+            .iter()
+            .map(Packet::checked_evaluate)
+            .collect::<Option<Vec<_>>>()?;
+        match kind {
+            OperatorKind::Sum => values
+                .into_iter()
+                .try_fold(0u128, |sum, value| sum.checked_add(value)),
+            OperatorKind::Product => values
+                .into_iter()
+                .try_fold(1u128, |product, value| product.checked_mul(value)),
+            OperatorKind::Min => values.into_iter().min(),
+            OperatorKind::Max => values.into_iter().max(),
+            OperatorKind::Gt => Some((values[0] > values[1]) as u128),
+            OperatorKind::Lt => Some((values[0] < values[1]) as u128),
+            OperatorKind::Eq => Some((values[0] == values[1]) as u128),
+        }
+    }
+
+    fn big_evaluate(&self) -> BigUint {
+        let (kind, children) = match self {
+            Packet::Literal { value, .. } => return value.clone(),
+            Packet::Operator { kind, children, .. } => (kind, children),
+        };
+
+        let values: Vec<BigUint> = children.iter().map(Packet::big_evaluate).collect();
+        let holds = match kind {
+            OperatorKind::Sum => {
+                return values
+                    .iter()
+                    .fold(BigUint::zero(), |sum, value| &sum + value)
             }
-            Operator::Gt(vals) | Operator::Lt(vals) | Operator::Eq(vals) => {
-                vals.push(val);
+            OperatorKind::Product => {
+                return values
+                    .iter()
+                    .fold(BigUint::one(), |product, value| &product * value)
             }
+            OperatorKind::Min => return values.into_iter().min().expect("Operators have operands"),
+            OperatorKind::Max => return values.into_iter().max().expect("Operators have operands"),
+            OperatorKind::Gt => values[0] > values[1],
+            OperatorKind::Lt => values[0] < values[1],
+            OperatorKind::Eq => values[0] == values[1],
+        };
+
+        if holds {
+            BigUint::one()
+        } else {
+            BigUint::zero()
         }
     }
 
-    fn val(&self) -> usize {
+    /// Builds an operator packet with the shorter length mode that can describe its children
+    fn operator(version: u8, kind: OperatorKind, children: Vec<Packet>) -> Self {
+        let length_mode = if children.len() < 1 << 11 {
+            LengthMode::PacketCount
+        } else {
+            LengthMode::TotalBits
+        };
+
+        Packet::Operator {
+            version,
+            kind,
+            length_mode,
+            children,
+        }
+    }
+
+    /// Number of packets in this tree, including itself
+    fn len(&self) -> usize {
         match self {
-            Operator::Sum(val)
-            | Operator::Product(val)
-            | Operator::Min(val)
-            | Operator::Max(val) => *val,
-            Operator::Gt(vals) => {
-                if vals[0] > vals[1] {
-                    1
-                } else {
-                    0
-                }
+            Packet::Literal { .. } => 1,
+            Packet::Operator { children, .. } => {
+                1 + children.iter().map(Packet::len).sum::<usize>()
             }
-            Operator::Lt(vals) => {
-                if vals[0] < vals[1] {
-                    1
-                } else {
-                    0
+        }
+    }
+}
+
+/// Every top-level packet in a transmission, along with the bit offset each packet in the
+/// transmission started at, in the order they were read
+struct Transmission {
+    packets: Vec<Packet>,
+    offsets: Vec<usize>,
+}
+
+impl Transmission {
+    fn version_sum(&self) -> usize {
+        self.packets.iter().map(Packet::version_sum).sum()
+    }
+
+    fn evaluate(&self) -> Result<BigUint, OperandCountError> {
+        self.packets.iter().try_fold(
+            BigUint::zero(),
+            |sum, packet| Ok(&sum + &packet.evaluate()?),
+        )
+    }
+}
+
+impl Display for Transmission {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn write_packet(
+            f: &mut std::fmt::Formatter<'_>,
+            packet: &Packet,
+            offsets: &mut impl Iterator<Item = usize>,
+            depth: usize,
+        ) -> std::fmt::Result {
+            let offset = offsets.next().expect("Every packet has an offset");
+            write!(f, "{:>8} {}", offset, "  ".repeat(depth))?;
+            match packet {
+                Packet::Literal { version, value } => writeln!(f, "v{} literal {}", version, value),
+                Packet::Operator {
+                    version,
+                    kind,
+                    length_mode,
+                    children,
+                } => {
+                    let length = match length_mode {
+                        LengthMode::TotalBits => "by bits",
+                        LengthMode::PacketCount => "by count",
+                    };
+                    writeln!(f, "v{} {} ({})", version, kind.name(), length)?;
+                    children
+                        .iter()
+                        .try_for_each(|child| write_packet(f, child, offsets, depth + 1))
                 }
             }
-            Operator::Eq(vals) => {
-                if vals[0] == vals[1] {
-                    1
-                } else {
-                    0
-                }
+        }
+
+        let mut offsets = self.offsets.iter().copied();
+        self.packets
+            .iter()
+            .try_for_each(|packet| write_packet(f, packet, &mut offsets, 0))
+    }
+}
+
+/// An operator with a number of operands it cannot take
+#[derive(Debug)]
+struct OperandCountError {
+    kind: OperatorKind,
+    count: usize,
+}
+
+impl Display for OperandCountError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Operator {} cannot take {} operands",
+            self.kind.name(),
+            self.count
+        )
+    }
+}
+
+impl Error for OperandCountError {}
+
+#[derive(Debug)]
+enum DecodeError {
+    /// A character of the transmission, counting from 0, that is not a hex digit
+    InvalidHexDigit {
+        position: usize,
+        digit: char,
+    },
+    Truncated(OutOfBits),
+    BadTypeId {
+        offset: usize,
+        type_id: usize,
+    },
+    WrongOperandCount {
+        offset: usize,
+        kind: OperatorKind,
+        count: usize,
+    },
+    /// The sub-packets of a length type 0 operator ran past the number of bits it gave
+    LengthMismatch {
+        offset: usize,
+        expected: usize,
+        actual: usize,
+    },
+    /// A packet nested more than `MAX_DEPTH` packets deep
+    TooDeep {
+        offset: usize,
+    },
+}
+
+impl From<OutOfBits> for DecodeError {
+    fn from(e: OutOfBits) -> Self {
+        DecodeError::Truncated(e)
+    }
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::InvalidHexDigit { position, digit } => {
+                write!(f, "Invalid hex digit '{}' at position {}", digit, position)
+            }
+            DecodeError::Truncated(e) => write!(f, "Truncated packet: {}", e),
+            DecodeError::BadTypeId { offset, type_id } => {
+                write!(f, "Bad type id {} in packet at bit {}", type_id, offset)
             }
+            DecodeError::WrongOperandCount {
+                offset,
+                kind,
+                count,
+            } => write!(
+                f,
+                "Operator {} at bit {} cannot take {} operands",
+                kind.name(),
+                offset,
+                count
+            ),
+            DecodeError::LengthMismatch {
+                offset,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Operator at bit {} gave {} bits of sub-packets but they took {}",
+                offset, expected, actual
+            ),
+            DecodeError::TooDeep { offset } => write!(
+                f,
+                "Packet at bit {} is nested more than {} packets deep",
+                offset, MAX_DEPTH
+            ),
         }
     }
 }
 
-fn parse(i: &mut usize, decoded: &[char], part_1: bool) -> usize {
-    let version = numeric_value(&decoded[*i..*i + 3]);
-    let type_id = numeric_value(&decoded[*i + 3..*i + 6]);
-    *i += 6;
+impl Error for DecodeError {}
+
+/// Reads the literal groups that follow a literal packet's header, only switching to
+/// arbitrary precision once the value outgrows a u128
+fn decode_literal(reader: &mut BitReader) -> Result<BigUint, OutOfBits> {
+    let mut value = 0u128;
+    let mut big_value = None;
+    let mut continues = true;
+    while continues {
+        continues = reader.read_bit()?;
+        let group = reader.read_bits(4)? as u128;
+        match &mut big_value {
+            None if value >> 124 == 0 => value = (value << 4) | group,
+            None => {
+                let shifted = &BigUint::from(value) * &BigUint::from(16u128);
+                big_value = Some(&shifted + &BigUint::from(group));
+            }
+            Some(big_value) => {
+                let shifted = &*big_value * &BigUint::from(16u128);
+                *big_value = &shifted + &BigUint::from(group);
+            }
+        }
+    }
+
+    Ok(big_value.unwrap_or_else(|| BigUint::from(value)))
+}
+
+/// Reads the next packet, `depth` packets deep, recording the offset of it and each of its
+/// sub-packets
+fn decode_packet(
+    reader: &mut BitReader,
+    offsets: &mut Vec<usize>,
+    depth: usize,
+) -> Result<Packet, DecodeError> {
+    let offset = reader.position();
+    if depth > MAX_DEPTH {
+        return Err(DecodeError::TooDeep { offset });
+    }
+    offsets.push(offset);
+    let version = reader.read_bits(3)? as u8;
+    let type_id = reader.read_bits(3)? as usize;
 
     if type_id == 4 {
-        // Literal
-        let mut continues = true;
+        let value = decode_literal(reader)?;
+        return Ok(Packet::Literal { version, value });
+    }
+
+    let kind =
+        OperatorKind::from_type_id(type_id).ok_or(DecodeError::BadTypeId { offset, type_id })?;
+    let mut children = Vec::new();
+    let length_mode = if !reader.read_bit()? {
+        let len = reader.read_bits(15)? as usize;
+        let start = reader.position();
+        while reader.position() < start + len {
+            children.push(decode_packet(reader, offsets, depth + 1)?);
+        }
+        if reader.position() != start + len {
+            return Err(DecodeError::LengthMismatch {
+                offset,
+                expected: len,
+                actual: reader.position() - start,
+            });
+        }
+        LengthMode::TotalBits
+    } else {
+        let len = reader.read_bits(11)?;
+        for _ in 0..len {
+            children.push(decode_packet(reader, offsets, depth + 1)?);
+        }
+        LengthMode::PacketCount
+    };
+
+    if !kind.accepts(children.len()) {
+        return Err(DecodeError::WrongOperandCount {
+            offset,
+            kind,
+            count: children.len(),
+        });
+    }
+
+    Ok(Packet::Operator {
+        version,
+        kind,
+        length_mode,
+        children,
+    })
+}
+
+fn decode(input: &str) -> Result<Transmission, DecodeError> {
+    let (bytes, len) = hex_to_bytes(input)?;
+    let mut reader = BitReader::new(&bytes, len);
+    let mut transmission = Transmission {
+        packets: Vec::new(),
+        offsets: Vec::new(),
+    };
+    // Zero bits could only start a sum with no operands, which is never valid, so a tail of
+    // zeros can only be padding
+    while !reader.rest_is_zero() {
+        let packet = decode_packet(&mut reader, &mut transmission.offsets, 1)?;
+        transmission.packets.push(packet);
+    }
+    Ok(transmission)
+}
 
-        let mut val = Vec::new();
-        while continues {
-            continues = decoded[*i] == '1';
-            let mut decoded: Vec<char> = decoded[*i + 1..*i + 5].iter().copied().collect();
-            val.append(&mut decoded);
+#[derive(Debug)]
+enum EncodeError {
+    VersionTooLarge(u8),
+    WrongOperandCount(OperandCountError),
+    TooManyBits(usize),
+    TooManyPackets(usize),
+}
 
-            *i += 5;
+impl Display for EncodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EncodeError::VersionTooLarge(version) => {
+                write!(f, "Version {} does not fit in 3 bits", version)
+            }
+            EncodeError::WrongOperandCount(e) => write!(f, "{}", e),
+            EncodeError::TooManyBits(bits) => {
+                write!(f, "{} bits of sub-packets do not fit in 15 bits", bits)
+            }
+            EncodeError::TooManyPackets(count) => {
+                write!(f, "{} sub-packets do not fit in 11 bits", count)
+            }
         }
-        let val = numeric_value(&val);
-        return if part_1 { version } else { val };
     }
+}
 
-    let mut op = if part_1 {
-        Operator::Sum(version)
-    } else {
-        match type_id {
-            0 => Operator::Sum(0),
-            1 => Operator::Product(1),
-            2 => Operator::Min(usize::MAX),
-            3 => Operator::Max(0),
-            5 => Operator::Gt(vec![]),
-            6 => Operator::Lt(vec![]),
-            7 => Operator::Eq(vec![]),
-            _ => panic!(),
+impl Error for EncodeError {}
+
+/// Appends the bits of a packet to `writer`, using as few literal groups as possible. The bit
+/// length of an operator's sub-packets is filled in once they have been written.
+fn encode_packet(packet: &Packet, writer: &mut BitWriter) -> Result<(), EncodeError> {
+    let (version, type_id) = match packet {
+        Packet::Literal { version, .. } => (*version, 4),
+        Packet::Operator {
+            version,
+            kind,
+            children,
+            ..
+        } => {
+            if !kind.accepts(children.len()) {
+                return Err(EncodeError::WrongOperandCount(OperandCountError {
+                    kind: *kind,
+                    count: children.len(),
+                }));
+            }
+            (*version, kind.type_id())
         }
     };
+    if version > 7 {
+        return Err(EncodeError::VersionTooLarge(version));
+    }
+    writer.write_bits(version as u64, 3);
+    writer.write_bits(type_id as u64, 3);
 
-    let length_id = decoded[*i];
-    *i += 1;
-    if length_id == '0' {
-        let len = numeric_value(&decoded[*i..*i + 15]);
-        *i += 15;
-        let stop_at = *i + len;
-        while *i < stop_at {
-            op.insert(parse(i, decoded, part_1));
-        }
-        assert!(*i == stop_at);
-    } else if length_id == '1' {
-        let len = numeric_value(&decoded[*i..*i + 11]);
-        *i += 11;
-        for _ in 0..len {
-            op.insert(parse(i, decoded, part_1));
+    match packet {
+        Packet::Literal { value, .. } => {
+            let groups = value.bit_len().div_ceil(4).max(1);
+            for group in (0..groups).rev() {
+                writer.write_bit(group != 0);
+                for bit in (group * 4..group * 4 + 4).rev() {
+                    writer.write_bit(value.bit(bit));
+                }
+            }
+        }
+        Packet::Operator {
+            length_mode: LengthMode::TotalBits,
+            children,
+            ..
+        } => {
+            writer.write_bit(false);
+            let length_at = writer.len();
+            writer.write_bits(0, 15);
+            for child in children {
+                encode_packet(child, writer)?;
+            }
+
+            let length = writer.len() - length_at - 15;
+            if length >= 1 << 15 {
+                return Err(EncodeError::TooManyBits(length));
+            }
+            writer.set_bits(length_at, length as u64, 15);
+        }
+        Packet::Operator {
+            length_mode: LengthMode::PacketCount,
+            children,
+            ..
+        } => {
+            if children.len() >= 1 << 11 {
+                return Err(EncodeError::TooManyPackets(children.len()));
+            }
+            writer.write_bit(true);
+            writer.write_bits(children.len() as u64, 11);
+            for child in children {
+                encode_packet(child, writer)?;
+            }
         }
     }
 
-    op.val()
+    Ok(())
 }
 
-pub fn solve(input: String, part_1: bool) -> usize {
-    let mut decoded = String::new();
-    for c in input.trim().chars() {
-        decoded += &format!("{:04b}", c.to_digit(16).unwrap());
+/// Encodes packets one after another as a hex transmission, padding the end with zero bits
+fn encode(packets: &[Packet]) -> Result<String, EncodeError> {
+    let mut writer = BitWriter::default();
+    for packet in packets {
+        encode_packet(packet, &mut writer)?;
     }
-    let mut i = 0;
-    let decoded: Vec<char> = decoded.chars().collect();
-    let mut version_sum = 0;
-    while i < decoded.len() {
-        if decoded.len() - i < 20 && numeric_value(&decoded[i..]) == 0 {
-            break;
+
+    Ok(writer.to_hex())
+}
+
+#[derive(Debug)]
+enum ParseExpressionError {
+    UnexpectedChar(char),
+    UnexpectedEnd,
+    UnknownFunction(String),
+    ValueTooLarge(String),
+}
+
+impl Display for ParseExpressionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseExpressionError::UnexpectedChar(c) => write!(f, "Unexpected '{}'", c),
+            ParseExpressionError::UnexpectedEnd => write!(f, "Unexpected end of expression"),
+            ParseExpressionError::UnknownFunction(name) => {
+                write!(f, "Unknown function '{}'", name)
+            }
+            ParseExpressionError::ValueTooLarge(digits) => {
+                write!(f, "Value {} is too large", digits)
+            }
+        }
+    }
+}
+
+impl Error for ParseExpressionError {}
+
+/// Recursive descent parser for arithmetic expressions, building the packets that evaluate to
+/// the same value. Every packet gets version 0.
+struct ExpressionParser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl ExpressionParser<'_> {
+    fn peek(&mut self) -> Option<char> {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+        self.chars.peek().copied()
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ParseExpressionError> {
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.chars.next();
+                Ok(())
+            }
+            Some(c) => Err(ParseExpressionError::UnexpectedChar(c)),
+            None => Err(ParseExpressionError::UnexpectedEnd),
+        }
+    }
+
+    /// `sum (('<' | '>' | '==') sum)?`
+    fn comparison(&mut self) -> Result<Packet, ParseExpressionError> {
+        let first = self.sum()?;
+        let kind = match self.peek() {
+            Some('<') => OperatorKind::Lt,
+            Some('>') => OperatorKind::Gt,
+            Some('=') => {
+                self.chars.next();
+                match self.chars.peek() {
+                    Some('=') => OperatorKind::Eq,
+                    Some(&c) => return Err(ParseExpressionError::UnexpectedChar(c)),
+                    None => return Err(ParseExpressionError::UnexpectedEnd),
+                }
+            }
+            _ => return Ok(first),
+        };
+        self.chars.next();
+
+        let second = self.sum()?;
+        Ok(Packet::operator(0, kind, vec![first, second]))
+    }
+
+    /// `product ('+' product)*`
+    fn sum(&mut self) -> Result<Packet, ParseExpressionError> {
+        let mut terms = vec![self.product()?];
+        while self.peek() == Some('+') {
+            self.chars.next();
+            terms.push(self.product()?);
+        }
+
+        Ok(match terms.len() {
+            1 => terms.pop().expect("One term was parsed"),
+            _ => Packet::operator(0, OperatorKind::Sum, terms),
+        })
+    }
+
+    /// `atom ('*' atom)*`
+    fn product(&mut self) -> Result<Packet, ParseExpressionError> {
+        let mut factors = vec![self.atom()?];
+        while self.peek() == Some('*') {
+            self.chars.next();
+            factors.push(self.atom()?);
+        }
+
+        Ok(match factors.len() {
+            1 => factors.pop().expect("One factor was parsed"),
+            _ => Packet::operator(0, OperatorKind::Product, factors),
+        })
+    }
+
+    /// A number, a bracketed expression, or `name(expression, ...)` for one of `sum`,
+    /// `product`, `min` and `max`
+    fn atom(&mut self) -> Result<Packet, ParseExpressionError> {
+        match self.peek() {
+            Some('(') => {
+                self.chars.next();
+                let packet = self.comparison()?;
+                self.expect(')')?;
+                Ok(packet)
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let mut digits = String::new();
+                while let Some(digit) = self.chars.next_if(char::is_ascii_digit) {
+                    digits.push(digit);
+                }
+                let value: u128 = digits
+                    .parse()
+                    .map_err(|_| ParseExpressionError::ValueTooLarge(digits))?;
+                Ok(Packet::Literal {
+                    version: 0,
+                    value: BigUint::from(value),
+                })
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                let mut name = String::new();
+                while let Some(letter) = self.chars.next_if(char::is_ascii_alphabetic) {
+                    name.push(letter);
+                }
+                let kind = match name.as_str() {
+                    "sum" => OperatorKind::Sum,
+                    "product" => OperatorKind::Product,
+                    "min" => OperatorKind::Min,
+                    "max" => OperatorKind::Max,
+                    _ => return Err(ParseExpressionError::UnknownFunction(name)),
+                };
+
+                self.expect('(')?;
+                let mut arguments = vec![self.comparison()?];
+                while self.peek() == Some(',') {
+                    self.chars.next();
+                    arguments.push(self.comparison()?);
+                }
+                self.expect(')')?;
+                Ok(Packet::operator(0, kind, arguments))
+            }
+            Some(c) => Err(ParseExpressionError::UnexpectedChar(c)),
+            None => Err(ParseExpressionError::UnexpectedEnd),
+        }
+    }
+}
+
+impl FromStr for Packet {
+    type Err = ParseExpressionError;
+
+    /// Parses an expression such as `max(1, 3*4) < 20`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = ExpressionParser {
+            chars: s.chars().peekable(),
+        };
+        let packet = parser.comparison()?;
+        match parser.peek() {
+            Some(c) => Err(ParseExpressionError::UnexpectedChar(c)),
+            None => Ok(packet),
         }
-        version_sum += parse(&mut i, &decoded, part_1);
     }
-    version_sum
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let input = input_parser::parse("puzzle16")[0].clone();
-    println!("Part 1: {}", solve(input.clone(), true));
-    println!("Part 2: {}", solve(input, false));
+    let transmission = decode(&input)?;
+    println!("Part 1: {}", transmission.version_sum());
+    println!("Part 2: {}", transmission.evaluate()?);
+
+    let packets: usize = transmission.packets.iter().map(Packet::len).sum();
+    println!("{} packets, starting with:", packets);
+    for line in transmission.to_string().lines().take(8) {
+        println!("{}", line);
+    }
+
+    let expression = "max(1, 3*4) < 20";
+    let packet: Packet = expression.parse()?;
+    let encoded = encode(std::slice::from_ref(&packet))?;
+    println!("{} encodes as {}", expression, encoded);
+    println!("which evaluates to {}", decode(&encoded)?.evaluate()?);
+    if let Err(e) = decode(&encoded[..encoded.len() - 4]) {
+        println!("Without its last 16 bits: {}", e);
+    }
+
+    let expression = "product(18446744073709551615, 18446744073709551615, 3) + 1";
+    println!(
+        "{} = {}",
+        expression,
+        expression.parse::<Packet>()?.evaluate()?
+    );
+
+    let literal = |value: u128| Packet::Literal {
+        version: 0,
+        value: BigUint::from(value),
+    };
+    let comparison = Packet::operator(
+        0,
+        OperatorKind::Gt,
+        vec![literal(1), literal(2), literal(3)],
+    );
+    if let Err(e) = comparison.evaluate() {
+        println!("Comparing three values: {}", e);
+    }
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Small xorshift generator, so round trips can be checked against many packet trees
+    struct Random(u64);
+
+    impl Random {
+        fn next(&mut self, bound: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % bound as u64) as usize
+        }
+
+        fn packet(&mut self, depth: usize) -> Packet {
+            let version = self.next(8) as u8;
+            if depth == 0 || self.next(3) == 0 {
+                // spread values over many sizes, including some too large for a u128
+                let mut value = BigUint::from(self.next(usize::MAX) as u128);
+                for _ in 0..self.next(6) {
+                    let shift = BigUint::from(1u128 << self.next(64));
+                    value = &(&value * &shift) + &BigUint::from(self.next(usize::MAX) as u128);
+                }
+                return Packet::Literal { version, value };
+            }
+
+            let kinds = [
+                OperatorKind::Sum,
+                OperatorKind::Product,
+                OperatorKind::Min,
+                OperatorKind::Max,
+                OperatorKind::Gt,
+                OperatorKind::Lt,
+                OperatorKind::Eq,
+            ];
+            let kind = kinds[self.next(kinds.len())];
+            let children = match kind {
+                OperatorKind::Gt | OperatorKind::Lt | OperatorKind::Eq => 2,
+                _ => 1 + self.next(4),
+            };
+            let length_mode = if self.next(2) == 0 {
+                LengthMode::TotalBits
+            } else {
+                LengthMode::PacketCount
+            };
+
+            Packet::Operator {
+                version,
+                kind,
+                length_mode,
+                children: (0..children).map(|_| self.packet(depth - 1)).collect(),
+            }
+        }
+    }
+
+    #[test]
+    fn input_round_trips() {
+        let input = input_parser::parse("puzzle16")[0].clone();
+        let transmission = decode(&input).expect("Input decodes");
+        let reencoded = encode(&transmission.packets).expect("Input encodes");
+        assert_eq!(
+            decode(&reencoded).expect("Re-encoding decodes").packets,
+            transmission.packets
+        );
+    }
+
+    #[test]
+    fn random_packets_round_trip() {
+        let mut random = Random(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..1000 {
+            let packets: Vec<Packet> = (0..1 + random.next(2)).map(|_| random.packet(4)).collect();
+            let encoded = encode(&packets).expect("Random packets encode");
+            assert_eq!(decode(&encoded).expect("Encoding decodes").packets, packets);
+        }
+    }
+
+    #[test]
+    fn expressions_round_trip() {
+        let packet: Packet = "max(1, 3*4) < 20".parse().expect("Expression parses");
+        let encoded = encode(std::slice::from_ref(&packet)).expect("Expression encodes");
+        let transmission = decode(&encoded).expect("Encoding decodes");
+        assert_eq!(transmission.packets, [packet]);
+        assert_eq!(transmission.evaluate().unwrap().to_u128(), Some(1));
+    }
+
+    #[test]
+    fn any_tail_of_zeros_is_padding() {
+        let transmission = decode("D2FE2800000").expect("Trailing zeros are padding");
+        assert_eq!(transmission.packets.len(), 1);
+        assert_eq!(transmission.evaluate().unwrap().to_u128(), Some(2021));
+        assert!(decode("D2FE2800010").is_err());
+    }
+
+    #[test]
+    fn non_hex_characters_are_reported() {
+        match decode("D2FE2G") {
+            Err(DecodeError::InvalidHexDigit { position, digit }) => {
+                assert_eq!((position, digit), (5, 'G'))
+            }
+            _ => panic!("'G' is not a hex digit"),
+        }
+    }
+
+    #[test]
+    fn hand_built_operators_with_the_wrong_operand_count_are_rejected() {
+        let literal = |value: u128| Packet::Literal {
+            version: 0,
+            value: BigUint::from(value),
+        };
+        let lone_comparison = Packet::operator(0, OperatorKind::Gt, vec![literal(1)]);
+        let empty_min = Packet::operator(0, OperatorKind::Min, vec![]);
+        let nested = Packet::operator(0, OperatorKind::Sum, vec![literal(1), empty_min.clone()]);
+        for packet in [lone_comparison, empty_min, nested] {
+            assert!(packet.evaluate().is_err());
+            assert!(matches!(
+                encode(&[packet]),
+                Err(EncodeError::WrongOperandCount(_))
+            ));
+        }
+    }
+
+    /// A transmission of `depth` packets, each a sum of the one inside it
+    fn nested_sums(depth: usize) -> String {
+        let mut writer = BitWriter::default();
+        for _ in 1..depth {
+            writer.write_bits(0, 6);
+            writer.write_bit(true);
+            writer.write_bits(1, 11);
+        }
+        writer.write_bits(4, 6);
+        writer.write_bits(1, 5);
+        writer.to_hex()
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error() {
+        let transmission = decode(&nested_sums(MAX_DEPTH)).expect("Nesting is within the limit");
+        assert_eq!(transmission.evaluate().unwrap().to_u128(), Some(1));
+
+        let too_deep = 18 * MAX_DEPTH;
+        assert!(matches!(
+            decode(&nested_sums(MAX_DEPTH + 1)),
+            Err(DecodeError::TooDeep { offset }) if offset == too_deep
+        ));
+        assert!(matches!(
+            decode(&nested_sums(100_000)),
+            Err(DecodeError::TooDeep { offset }) if offset == too_deep
+        ));
+    }
+
+    #[test]
+    fn equality_needs_two_equals_signs() {
+        for expression in ["2 == 2", "1==2", "3*2 == 6"] {
+            assert!(expression.parse::<Packet>().is_ok(), "{}", expression);
+        }
+        for expression in ["3 = 3", "2 =* 2", "2 =x 3", "2 = = 2", "2 ="] {
+            assert!(expression.parse::<Packet>().is_err(), "{}", expression);
+        }
+    }
+}
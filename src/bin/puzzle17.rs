@@ -1,73 +1,412 @@
-use std::{
-    cmp::Ordering,
-    error::Error,
-    ops::{Add, Div, RangeInclusive},
-};
+use std::{cmp::Ordering, error::Error, fmt::Display, fs, ops::RangeInclusive};
 use utils::input_parser;
 
+type Position = (i64, i64);
+
+/// How a probe's velocity changes after every step. Build it with `Physics::new` so probes are
+/// known to eventually fall.
+#[derive(Debug, Clone, Copy)]
+struct Physics {
+    /// Taken off the vertical velocity
+    gravity: i64,
+    /// Taken off the horizontal velocity towards zero, without going past it
+    drag: i64,
+    /// Added to the velocity after drag and gravity
+    wind: (i64, i64),
+}
+
+impl Default for Physics {
+    fn default() -> Self {
+        Self {
+            gravity: 1,
+            drag: 1,
+            wind: (0, 0),
+        }
+    }
+}
+
+/// Physics where a probe launched upwards could keep rising forever
+#[derive(Debug)]
+struct NeverFallsError {
+    gravity: i64,
+    updraft: i64,
+}
+
+impl Display for NeverFallsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Gravity {} must be stronger than an updraft of {}",
+            self.gravity, self.updraft
+        )
+    }
+}
+
+impl Error for NeverFallsError {}
+
+impl Physics {
+    fn new(gravity: i64, drag: i64, wind: (i64, i64)) -> Result<Self, NeverFallsError> {
+        let physics = Self {
+            gravity,
+            drag,
+            wind,
+        };
+        if physics.falls() {
+            Ok(physics)
+        } else {
+            Err(NeverFallsError {
+                gravity,
+                updraft: wind.1,
+            })
+        }
+    }
+
+    /// Whether a probe always ends up falling, so it eventually drops below any target
+    fn falls(&self) -> bool {
+        self.gravity > self.wind.1
+    }
+
+    fn launch(&self, velocity: (i64, i64)) -> Trajectory {
+        Trajectory {
+            physics: *self,
+            position: (0, 0),
+            velocity,
+        }
+    }
+}
+
+/// Every position a probe passes through, starting from where it was launched
+struct Trajectory {
+    physics: Physics,
+    position: Position,
+    velocity: (i64, i64),
+}
+
+impl Trajectory {
+    fn velocity(&self) -> (i64, i64) {
+        self.velocity
+    }
+}
+
+impl Iterator for Trajectory {
+    type Item = Position;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let position = self.position;
+        let (dx, dy) = self.velocity;
+        self.position = (position.0 + dx, position.1 + dy);
+
+        let dx = match dx.cmp(&0) {
+            Ordering::Greater => (dx - self.physics.drag).max(0),
+            Ordering::Less => (dx + self.physics.drag).min(0),
+            _ => 0,
+        };
+        self.velocity = (
+            dx + self.physics.wind.0,
+            dy - self.physics.gravity + self.physics.wind.1,
+        );
+
+        Some(position)
+    }
+}
+
 struct Target {
     pub x_range: (i64, i64),
     pub y_range: (i64, i64),
 }
 
 impl Target {
-    pub fn max_y_if_hits(&self, velocity_x: i64, velocity_y: i64) -> Option<i64> {
-        let (mut dx, mut dy) = (velocity_x, velocity_y);
-        let (mut peak, mut x, mut y) = (0, 0, 0);
-        loop {
-            x += dx;
-            y += dy;
-
-            dx += match dx.cmp(&0) {
-                Ordering::Greater => -1,
-                Ordering::Less => 1,
-                _ => 0,
-            };
-
-            dy -= 1;
-
-            peak = peak.max(y);
-
-            // if we've hit the target box
-            if x >= self.x_range.0
-                && x <= self.x_range.1
-                && y >= self.y_range.0
-                && y <= self.y_range.1
-            {
-                return Some(peak);
-            }
+    pub fn contains(&self, (x, y): Position) -> bool {
+        x >= self.x_range.0 && x <= self.x_range.1 && y >= self.y_range.0 && y <= self.y_range.1
+    }
 
-            if x > self.x_range.1 && y > self.y_range.1 {
-                return None;
-            }
+    pub fn max_y_if_hits(
+        &self,
+        physics: &Physics,
+        velocity_x: i64,
+        velocity_y: i64,
+    ) -> Option<i64> {
+        max_y_if_hits_targets(
+            std::slice::from_ref(self),
+            physics,
+            (velocity_x, velocity_y),
+            Goal::All,
+        )
+    }
+}
 
-            if dx == 0 && !(x >= self.x_range.0 && x <= self.x_range.1) {
-                return None;
-            }
+/// Which of several targets a probe has to pass through to count as a hit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Goal {
+    /// At least one of them
+    Any,
+    /// Every one of them, in any order
+    All,
+    /// Every one of them, in the order given. A single position can count for several targets
+    /// in a row.
+    InOrder,
+}
 
-            if dy < 0 && y < self.y_range.0 {
-                return None;
-            }
+/// Highest point a probe reaches by the time it has met `goal` for the targets, or `None` if
+/// it never does
+fn max_y_if_hits_targets(
+    targets: &[Target],
+    physics: &Physics,
+    velocity: Position,
+    goal: Goal,
+) -> Option<i64> {
+    debug_assert!(
+        physics.falls(),
+        "Physics::new only builds physics where probes fall"
+    );
+    let mut hit = vec![false; targets.len()];
+    let mut next_in_order = 0;
+    let is_met = |hit: &[bool], next_in_order: usize| match goal {
+        Goal::Any => hit.contains(&true),
+        Goal::All => !hit.contains(&false),
+        Goal::InOrder => next_in_order == targets.len(),
+    };
+
+    let mut peak = 0;
+    let mut trajectory = physics.launch(velocity);
+    // the launch point itself does not count as a hit
+    trajectory.next();
+    while !is_met(&hit, next_in_order) {
+        let position = trajectory.next().expect("Trajectories never end");
+        peak = peak.max(position.1);
+        for (hit, target) in hit.iter_mut().zip(targets) {
+            *hit |= target.contains(position);
+        }
+        while next_in_order < targets.len() && targets[next_in_order].contains(position) {
+            next_in_order += 1;
+        }
+        if is_met(&hit, next_in_order) {
+            break;
+        }
+
+        // Once falling, the probe can never climb back up to a target it still needs
+        let lowest = targets
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| match goal {
+                Goal::Any => true,
+                Goal::All => !hit[i],
+                Goal::InOrder => i >= next_in_order,
+            })
+            .map(|(_, target)| target.y_range.0)
+            .min();
+        if trajectory.velocity().1 < 0 && lowest.is_none_or(|lowest| position.1 < lowest) {
+            return None;
         }
     }
+
+    Some(peak)
+}
+
+/// Draws a trajectory over the targets, marking the launch point `S`, the probe `#` and the
+/// targets `T`
+fn render(trajectory: &[Position], targets: &[Target]) -> String {
+    let xs = trajectory.iter().map(|position| position.0).chain(
+        targets
+            .iter()
+            .flat_map(|target| [target.x_range.0, target.x_range.1]),
+    );
+    let ys = trajectory.iter().map(|position| position.1).chain(
+        targets
+            .iter()
+            .flat_map(|target| [target.y_range.0, target.y_range.1]),
+    );
+    let (min_x, max_x) = (xs.clone().min().unwrap_or(0), xs.max().unwrap_or(0));
+    let (min_y, max_y) = (ys.clone().min().unwrap_or(0), ys.max().unwrap_or(0));
+
+    let mut picture = String::new();
+    for y in (min_y..=max_y).rev() {
+        for x in min_x..=max_x {
+            picture.push(if (x, y) == (0, 0) {
+                'S'
+            } else if trajectory.contains(&(x, y)) {
+                '#'
+            } else if targets.iter().any(|target| target.contains((x, y))) {
+                'T'
+            } else {
+                '.'
+            });
+        }
+        picture.push('\n');
+    }
+    picture
+}
+
+fn to_csv(trajectory: &[Position]) -> String {
+    let mut csv = String::from("step,x,y\n");
+    for (step, (x, y)) in trajectory.iter().enumerate() {
+        csv += &format!("{},{},{}\n", step, x, y);
+    }
+    csv
+}
+
+/// Inclusive range of steps, where an end of `None` means the range never ends
+type Steps = (i64, Option<i64>);
+
+/// Range of steps `t` for which `t * (b - t) / 2`, the height after `t` steps of a probe
+/// launched upwards at `(b - 1) / 2` under the default physics, is at least `c`. The height
+/// rises and falls symmetrically about step `b / 2`, so this is the integer range between the
+/// roots of `t^2 - b t + 2c`. The range may include steps before the launch.
+fn steps_at_least(b: i64, c: i64) -> Option<(i64, i64)> {
+    let discriminant = b * b - 8 * c;
+    if discriminant < 0 {
+        return None;
+    }
+
+    // Rounding in the square root can leave the estimate of the lower root a step out either way
+    let reaches = |t: i64| t * (b - t) >= 2 * c;
+    let mut first = (b - discriminant.isqrt()).div_euclid(2);
+    while reaches(first - 1) {
+        first -= 1;
+    }
+    while !reaches(first) && 2 * first <= b {
+        first += 1;
+    }
+
+    (2 * first <= b).then_some((first, b - first))
 }
 
-fn compute_velocity_bounds(target: &Target) -> (RangeInclusive<i64>, RangeInclusive<i64>) {
-    let x_lower_bound = 0;
-    let x_upper_bound = target.x_range.1.pow(2).add(target.x_range.1).div(2);
-    let y_lower_bound = target.y_range.0;
-    let y_upper_bound = ((-target.y_range.0) - 1).abs();
-    (x_lower_bound..=x_upper_bound, y_lower_bound..=y_upper_bound)
+/// Steps at which a probe launched with horizontal velocity `dx` is within the target's x
+/// range under the default physics. Drag only ever moves x one way until it stops, so these
+/// are always one range.
+fn x_steps(target: &Target, dx: i64) -> Option<Steps> {
+    // Mirror probes launched to the left, so x only ever grows
+    let (dx, (low, high)) = if dx < 0 {
+        (-dx, (-target.x_range.1, -target.x_range.0))
+    } else {
+        (dx, target.x_range)
+    };
+
+    // x follows the same curve as a height until drag stops it at step dx
+    let b = 2 * dx + 1;
+    let rest = dx * (dx + 1) / 2;
+    if rest < low || high < 0 {
+        return None;
+    }
+
+    let first = steps_at_least(b, low).map_or(1, |(first, _)| first.max(1));
+    if rest <= high {
+        return Some((first, None));
+    }
+
+    let (overshoot, _) = steps_at_least(b, high + 1).expect("x comes to rest past the range");
+    (first < overshoot).then_some((first, Some(overshoot - 1)))
 }
 
-fn solve(target: &Target) -> (i64, i64) {
+/// Steps at which a probe launched with vertical velocity `dy` is within the target's y
+/// range under the default physics. y rises and then falls, so these are at most one range on
+/// the way up and one on the way down.
+fn y_steps(target: &Target, dy: i64) -> Vec<Steps> {
+    let b = 2 * dy + 1;
+    let (first, last) = match steps_at_least(b, target.y_range.0) {
+        Some((first, last)) => (first.max(1), last),
+        None => return Vec::new(),
+    };
+
+    // Cut out the steps spent above the range
+    let ranges = match steps_at_least(b, target.y_range.1 + 1) {
+        Some((above, below)) => vec![(first, last.min(above - 1)), (first.max(below + 1), last)],
+        None => vec![(first, last)],
+    };
+    ranges
+        .into_iter()
+        .filter(|&(first, last)| first <= last)
+        .map(|(first, last)| (first, Some(last)))
+        .collect()
+}
+
+/// Earliest step in both ranges
+fn first_common_step(a: Steps, b: Steps) -> Option<i64> {
+    let start = a.0.max(b.0);
+    let end = match (a.1, b.1) {
+        (Some(a), Some(b)) => a.min(b),
+        (Some(end), None) | (None, Some(end)) => end,
+        (None, None) => i64::MAX,
+    };
+    (start <= end).then_some(start)
+}
+
+/// Highest point reached by a probe launched with vertical velocity `dy` by the end of `step`
+fn peak_by(dy: i64, step: i64) -> i64 {
+    let rising = step.min(dy.max(0));
+    rising * dy - rising * (rising - 1) / 2
+}
+
+/// Finds every launch velocity that hits the target without simulating any launch, by working
+/// out which steps each horizontal and each vertical velocity spends within range and
+/// intersecting them. Returns the highest point reached before hitting along with the number
+/// of velocities that hit, or `None` if infinitely many do. Only holds for the default physics,
+/// with gravity and drag of 1 and no wind.
+fn solve(target: &Target) -> Option<(i64, i64)> {
+    // Any faster and the first step overshoots
+    let dx_bounds = target.x_range.0.min(0)..=target.x_range.1.max(0);
+    let x_steps: Vec<Steps> = dx_bounds.filter_map(|dx| x_steps(target, dx)).collect();
+
+    // On the way down a probe passes through every height it rose through, including 0, so
+    // launching ever higher still hits if x can come to rest in range at height 0
+    let straddles_origin = target.y_range.0 <= 0 && target.y_range.1 >= 0;
+    if straddles_origin && x_steps.iter().any(|&(_, end)| end.is_none()) {
+        return None;
+    }
+
+    // Any faster and the probe is above the target for as long as it is in the x range, or
+    // falls past the target on its way back down
+    let last_x_step = x_steps
+        .iter()
+        .filter_map(|&(_, end)| end)
+        .max()
+        .unwrap_or(0);
+    let dy_upper_bound = if straddles_origin {
+        target
+            .y_range
+            .0
+            .abs()
+            .max(target.y_range.1)
+            .max(last_x_step)
+    } else {
+        target.y_range.0.abs().max(target.y_range.1.abs())
+    };
+    let dy_bounds = target.y_range.0.min(0)..=dy_upper_bound;
+
+    let mut max_y_optimal_path = 0;
+    let mut num_solutions = 0;
+    for dy in dy_bounds {
+        let y_steps = y_steps(target, dy);
+        if y_steps.is_empty() {
+            continue;
+        }
+
+        for &x_range in &x_steps {
+            let first_hit = y_steps
+                .iter()
+                .filter_map(|&y_range| first_common_step(x_range, y_range))
+                .min();
+            if let Some(step) = first_hit {
+                max_y_optimal_path = max_y_optimal_path.max(peak_by(dy, step));
+                num_solutions += 1;
+            }
+        }
+    }
+    Some((max_y_optimal_path, num_solutions))
+}
+
+/// Simulates every launch velocity within the bounds, returning the highest point reached by
+/// any that hit along with how many hit
+fn simulate_all(
+    x_bounds: RangeInclusive<i64>,
+    y_bounds: RangeInclusive<i64>,
+    max_y_if_hits: impl Fn(i64, i64) -> Option<i64>,
+) -> (i64, i64) {
     let mut num_solutions = 0;
     let mut max_y_optimal_path = 0;
-    let (x_bounds, y_bounds) = compute_velocity_bounds(target);
-    println!("{:?}, {:?}", y_bounds, x_bounds);
     x_bounds.for_each(|dx| {
         y_bounds.clone().for_each(|dy| {
-            if let Some(max_y) = target.max_y_if_hits(dx, dy) {
+            if let Some(max_y) = max_y_if_hits(dx, dy) {
                 max_y_optimal_path = max_y_optimal_path.max(max_y);
                 num_solutions += 1;
             }
@@ -76,8 +415,7 @@ fn solve(target: &Target) -> (i64, i64) {
     (max_y_optimal_path, num_solutions)
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let input = input_parser::parse("puzzle17")[0].clone();
+fn parse(input: &str) -> Target {
     let (x_str, y_str) = input
         .trim()
         .trim_start_matches("target area: ")
@@ -95,12 +433,273 @@ fn main() -> Result<(), Box<dyn Error>> {
     let x2: i64 = x2_str.parse().expect("Failed to parse i64");
     let y1: i64 = y1_str.parse().expect("Failed to parse i64");
     let y2: i64 = y2_str.parse().expect("Failed to parse i64");
-    let target = Target {
+    Target {
         x_range: (x1.min(x2), x1.max(x2)),
         y_range: (y1.min(y2), y1.max(y2)),
-    };
-    let (part_1, part_2) = solve(&target);
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let target = parse(&input_parser::parse("puzzle17")[0]);
+    let (part_1, part_2) = solve(&target).expect("Infinitely many velocities hit the target");
     println!("Part 1: {}", part_1);
     println!("Part 2: {}", part_2);
+
+    for (name, x_range, y_range) in [
+        ("above the origin", (20, 30), (5, 10)),
+        ("straddling the origin", (20, 30), (-5, 5)),
+        (
+            "straddling the origin out of reach of resting x",
+            (22, 27),
+            (-5, 5),
+        ),
+        ("behind the origin", (-30, -20), (-10, -5)),
+        ("around the origin", (-2, 2), (-3, 3)),
+    ] {
+        match solve(&Target { x_range, y_range }) {
+            Some((highest, count)) => println!(
+                "Target {}: {} velocities, reaching up to {}",
+                name, count, highest
+            ),
+            None => println!("Target {}: infinitely many velocities", name),
+        }
+    }
+
+    // A crosswind blowing back towards the launch point, with a second target to pass through
+    // on the way down to the first
+    let physics = Physics::new(2, 1, (-1, 0))?;
+    let waypoint = Target {
+        x_range: (target.x_range.0 / 2, target.x_range.1 / 2),
+        y_range: (-10, 10),
+    };
+    let targets = [waypoint, target];
+    let bound = targets[1].x_range.1.max(targets[1].y_range.0.abs());
+    let (highest, count) = simulate_all(0..=bound, -bound..=bound, |dx, dy| {
+        max_y_if_hits_targets(&targets, &physics, (dx, dy), Goal::InOrder)
+    });
+    println!(
+        "With gravity 2 and a headwind: {} velocities through both targets, reaching up to {}",
+        count, highest
+    );
+    for (goal, description) in [
+        (Goal::All, "both targets in any order"),
+        (Goal::Any, "either target"),
+    ] {
+        let (_, count) = simulate_all(0..=bound, -bound..=bound, |dx, dy| {
+            max_y_if_hits_targets(&targets, &physics, (dx, dy), goal)
+        });
+        println!("  {} velocities through {}", count, description);
+    }
+
+    let shot = (0..=bound)
+        .flat_map(|dx| (-bound..=bound).map(move |dy| (dx, dy)))
+        .find(|&velocity| {
+            max_y_if_hits_targets(&targets, &physics, velocity, Goal::InOrder).is_some()
+        });
+    if let Some(velocity) = shot {
+        let lowest = targets[1].y_range.0;
+        let arc: Vec<Position> = physics
+            .launch(velocity)
+            .take_while(|position| position.1 >= lowest)
+            .collect();
+        println!(
+            "Launching at {:?} passes through {} positions",
+            velocity,
+            arc.len()
+        );
+        if let Some(csv_path) = std::env::args().nth(1) {
+            fs::write(csv_path, to_csv(&arc))?;
+        }
+    }
+
+    // The example from the puzzle, small enough to draw
+    let example = [Target {
+        x_range: (20, 30),
+        y_range: (-10, -5),
+    }];
+    let arc: Vec<Position> = Physics::default()
+        .launch((7, 2))
+        .take_while(|position| position.1 >= example[0].y_range.0)
+        .collect();
+    if let Some(highest) = example[0].max_y_if_hits(&Physics::default(), 7, 2) {
+        println!("Launching at (7, 2) reaches up to {}", highest);
+    }
+    print!("{}", render(&arc, &example));
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tries every launch velocity that could hit the target, simulating each one. Any faster
+    /// than the furthest edge of the target from the origin and the probe either overshoots on
+    /// the first step or is above the target until it is back at height 0, by which time x has
+    /// stopped moving.
+    fn brute_force(target: &Target) -> (i64, i64) {
+        let bound = [target.x_range, target.y_range]
+            .iter()
+            .flat_map(|&(low, high)| [low.abs(), high.abs()])
+            .max()
+            .unwrap_or(0)
+            + 1;
+        let physics = Physics::default();
+        simulate_all(-bound..=bound, -bound..=bound, |dx, dy| {
+            target.max_y_if_hits(&physics, dx, dy)
+        })
+    }
+
+    /// Brute force steps at which `values` is within `range`, as ranges
+    fn step_ranges(values: impl Iterator<Item = i64>, (low, high): (i64, i64)) -> Vec<Steps> {
+        let mut ranges: Vec<Steps> = Vec::new();
+        for (step, value) in (1..).zip(values) {
+            if value < low || value > high {
+                continue;
+            }
+            match ranges.last_mut() {
+                Some((_, Some(end))) if *end == step - 1 => *end = step,
+                _ => ranges.push((step, Some(step))),
+            }
+        }
+        ranges
+    }
+
+    #[test]
+    fn step_ranges_match_simulating_each_axis() {
+        const STEPS: usize = 200;
+        for low in -30..=30 {
+            for high in low..=low + 12 {
+                let target = Target {
+                    x_range: (low, high),
+                    y_range: (low, high),
+                };
+                for velocity in -20..=20 {
+                    let mut positions: Vec<Position> = Physics::default()
+                        .launch((velocity, velocity))
+                        .skip(1)
+                        .take(STEPS)
+                        .collect();
+
+                    let mut expected = step_ranges(positions.iter().map(|p| p.0), target.x_range);
+                    // x is at rest by the end, so a range still open then never ends
+                    if let Some((_, end)) = expected.last_mut() {
+                        if *end == Some(STEPS as i64) {
+                            *end = None;
+                        }
+                    }
+                    assert!(expected.len() <= 1);
+                    assert_eq!(x_steps(&target, velocity), expected.first().copied());
+
+                    positions.truncate(STEPS / 2);
+                    let expected = step_ranges(positions.iter().map(|p| p.1), target.y_range);
+                    assert_eq!(y_steps(&target, velocity), expected, "dy={}", velocity);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn physics_without_enough_gravity_is_rejected() {
+        assert!(Physics::new(1, 1, (0, 0)).is_ok());
+        assert!(Physics::new(1, 1, (0, 1)).is_err());
+        assert!(Physics::new(0, 1, (3, 0)).is_err());
+    }
+
+    #[test]
+    fn multiple_target_goals_combine_single_target_hits() {
+        let target = |x_range, y_range| Target { x_range, y_range };
+        let targets = [target((20, 30), (-10, -5)), target((8, 14), (2, 6))];
+        let reversed = [target((8, 14), (2, 6)), target((20, 30), (-10, -5))];
+        let gusty = Physics::new(2, 1, (-1, 1)).expect("Gravity beats the updraft");
+        for physics in [Physics::default(), gusty] {
+            let mut through_both = 0;
+            for dx in 0..=35 {
+                for dy in -12..=15 {
+                    let velocity = (dx, dy);
+                    let hits = |targets: &[Target], goal| {
+                        max_y_if_hits_targets(targets, &physics, velocity, goal)
+                    };
+                    let singles = [
+                        hits(&targets[..1], Goal::All),
+                        hits(&targets[1..], Goal::All),
+                    ];
+                    let in_order = hits(&targets, Goal::InOrder);
+                    let in_reverse = hits(&reversed, Goal::InOrder);
+
+                    // The peak only grows, so it is reached by the first or last target hit
+                    let first = singles.iter().flatten().min().copied();
+                    let last = match singles {
+                        [Some(a), Some(b)] => Some(a.max(b)),
+                        _ => None,
+                    };
+                    assert_eq!(hits(&targets, Goal::Any), first, "{:?}", velocity);
+                    assert_eq!(hits(&targets, Goal::All), last, "{:?}", velocity);
+                    assert_eq!(hits(&reversed, Goal::All), last, "{:?}", velocity);
+                    assert_eq!(
+                        in_order.is_some() || in_reverse.is_some(),
+                        last.is_some(),
+                        "{:?}",
+                        velocity
+                    );
+                    // Keeping to an order can mean waiting for a later pass through a target
+                    assert!(in_order
+                        .into_iter()
+                        .chain(in_reverse)
+                        .all(|peak| Some(peak) >= last));
+                    through_both += usize::from(last.is_some());
+                }
+            }
+            assert!(through_both > 0);
+        }
+
+        let no_targets = |goal| max_y_if_hits_targets(&[], &Physics::default(), (3, 4), goal);
+        assert_eq!(no_targets(Goal::Any), None);
+        assert_eq!(no_targets(Goal::All), Some(0));
+        assert_eq!(no_targets(Goal::InOrder), Some(0));
+    }
+
+    #[test]
+    fn solving_agrees_with_simulating_the_input() {
+        let target = parse(&input_parser::parse("puzzle17")[0]);
+        assert_eq!(solve(&target), Some(brute_force(&target)));
+    }
+
+    #[test]
+    fn solving_agrees_with_simulating_the_example() {
+        let target = parse("target area: x=20..30, y=-10..-5");
+        assert_eq!(solve(&target), Some((45, 112)));
+        assert_eq!(brute_force(&target), (45, 112));
+    }
+
+    #[test]
+    fn solving_agrees_with_simulating_small_targets() {
+        // Every quadrant, and targets straddling either axis or both
+        for left in (-12..=12).step_by(3) {
+            for right in [left, left + 4] {
+                for bottom in (-9..=7).step_by(2) {
+                    for top in [bottom, bottom + 3] {
+                        let target = Target {
+                            x_range: (left, right),
+                            y_range: (bottom, top),
+                        };
+                        let description = format!("x={}..{}, y={}..{}", left, right, bottom, top);
+                        match solve(&target) {
+                            Some(solution) => {
+                                assert_eq!(solution, brute_force(&target), "{}", description)
+                            }
+                            // x comes to rest in range, and every launch high enough passes
+                            // back through height 0 there
+                            None => assert!(
+                                (-20..=20).any(|dx| target
+                                    .max_y_if_hits(&Physics::default(), dx, 1_000)
+                                    .is_some()),
+                                "{}",
+                                description
+                            ),
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
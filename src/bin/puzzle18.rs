@@ -69,7 +69,7 @@ impl Tree {
                 continue;
             }
 
-            let (a, b) = if v % 2 == 0 {
+            let (a, b) = if v.is_multiple_of(2) {
                 (v / 2, v / 2)
             } else {
                 (v / 2, v / 2 + 1)
@@ -143,7 +143,7 @@ fn part_2(input: &[String]) -> u32 {
     for a in trees.iter() {
         for b in trees.iter() {
             let mut a = a.clone();
-            a.merge(&b);
+            a.merge(b);
             a.reduce();
             best_score = best_score.max(a.score());
         }
@@ -55,11 +55,7 @@ impl DiagnosticReport {
             let (gamma, epsilon) = report.compute_gamma_epsilon();
             let rating = if use_gamma { gamma } else { epsilon };
 
-            report.values = report
-                .values
-                .into_iter()
-                .filter(|n| rating & mask == n & mask)
-                .collect();
+            report.values.retain(|n| rating & mask == n & mask);
 
             mask >>= 1;
         }
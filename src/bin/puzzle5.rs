@@ -1,4 +1,4 @@
-use std::{collections::HashMap, error::Error, num::ParseIntError, str::FromStr};
+use std::{collections::HashMap, error::Error, fmt::Display, num::ParseIntError, str::FromStr};
 use utils::input_parser;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -18,6 +18,15 @@ impl From<ParseIntError> for ParsePointError {
     }
 }
 
+impl Display for ParsePointError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParsePointError::NoDelimeter => write!(f, "No ',' delimeter found in point"),
+            ParsePointError::ParseIntError(e) => write!(f, "Failed to parse coordinate: {}", e),
+        }
+    }
+}
+
 impl FromStr for Point {
     type Err = ParsePointError;
 
@@ -50,6 +59,15 @@ impl From<ParsePointError> for ParseLineSegmentError {
     }
 }
 
+impl Display for ParseLineSegmentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseLineSegmentError::NoDelimeter => write!(f, "No ' -> ' delimeter found in line"),
+            ParseLineSegmentError::ParsePointError(e) => write!(f, "Failed to parse point: {}", e),
+        }
+    }
+}
+
 impl FromStr for LineSegment {
     type Err = ParseLineSegmentError;
 
@@ -94,7 +112,11 @@ fn main() -> Result<(), Box<dyn Error>> {
     let inputs = input_parser::parse("puzzle5");
     let line_segments: Vec<LineSegment> = inputs
         .iter()
-        .map(|input| input.parse().expect("Unable to parse line"))
+        .map(|input| {
+            input
+                .parse()
+                .unwrap_or_else(|e| panic!("Unable to parse line: {}", e))
+        })
         .collect();
 
     println!("Part 1: {}", compute(&line_segments, false)?);
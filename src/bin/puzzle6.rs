@@ -55,7 +55,6 @@ fn main() -> Result<(), Box<dyn Error>> {
     let input = input_parser::parse("puzzle6");
     let mut initial_timers = input[0]
         .split(',')
-        .into_iter()
         .map(|val| val.parse::<usize>().expect("Failed to parse timer input"))
         .collect::<Vec<usize>>();
     initial_timers.sort_unstable();
@@ -6,7 +6,6 @@ where
     F: Fn(i32, i32) -> i32,
 {
     (*inputs.iter().min().unwrap()..(*inputs.iter().max().unwrap() + 1))
-        .into_iter()
         .map(|movement| {
             inputs
                 .iter()
@@ -1,33 +1,187 @@
 use std::{
-    collections::{HashMap, HashSet},
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
     error::Error,
+    fmt::Display,
     ops::Index,
 };
 use utils::input_parser;
 
 const MAX_HEIGHT: u32 = 9;
 
+type Position = (i32, i32);
+
+/// Disjoint-set forest over cell indices, with path compression and union by size.
+struct UnionFind {
+    parents: Vec<usize>,
+    sizes: Vec<usize>,
+}
+
+impl UnionFind {
+    pub fn new(len: usize) -> Self {
+        Self {
+            parents: (0..len).collect(),
+            sizes: vec![1; len],
+        }
+    }
+
+    pub fn add(&mut self) -> usize {
+        self.parents.push(self.parents.len());
+        self.sizes.push(1);
+        self.parents.len() - 1
+    }
+
+    pub fn find(&mut self, mut node: usize) -> usize {
+        let mut root = node;
+        while self.parents[root] != root {
+            root = self.parents[root];
+        }
+
+        // Compress the path so later lookups are O(1)
+        while self.parents[node] != root {
+            let next = self.parents[node];
+            self.parents[node] = root;
+            node = next;
+        }
+
+        root
+    }
+
+    pub fn union(&mut self, a: usize, b: usize) {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+
+        if self.sizes[a] < self.sizes[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+
+        self.parents[b] = a;
+        self.sizes[a] += self.sizes[b];
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Basin {
+    pub id: usize,
+    pub low_point: Position,
+    pub cells: Vec<Position>,
+    pub size: usize,
+    /// Number of cell edges that border a wall, another basin, or the edge of the map
+    pub perimeter: usize,
+    /// Inclusive `(min, max)` corners of the basin
+    pub bounding_box: (Position, Position),
+}
+
+struct BasinAnalysis {
+    pub basins: Vec<Basin>,
+    /// Basin id of every cell in row-major order, `None` for walls
+    pub labels: Vec<Option<usize>>,
+    width: usize,
+}
+
+impl BasinAnalysis {
+    pub fn label(&self, pos: Position) -> Option<usize> {
+        if pos.0 < 0 || pos.1 < 0 || pos.0 as usize >= self.width {
+            return None;
+        }
+
+        self.labels
+            .get(pos.1 as usize * self.width + pos.0 as usize)
+            .copied()
+            .flatten()
+    }
+
+    pub fn largest(&self, count: usize) -> Vec<&Basin> {
+        let mut basins: Vec<&Basin> = self.basins.iter().collect();
+        basins.sort_unstable_by(|a, b| b.size.cmp(&a.size).then(a.id.cmp(&b.id)));
+        basins.truncate(count);
+        basins
+    }
+}
+
+/// Segmentation of the whole map into catchments, one per local minimum. Unlike
+/// [`BasinAnalysis`], basins are separated by ridges of any height rather than by walls of 9s.
+struct Watershed {
+    pub minima: Vec<Position>,
+    /// Catchment id of every cell in row-major order
+    pub labels: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Reservoir {
+    pub basin: usize,
+    /// Water level at which the basin starts to overflow
+    pub spill_height: u32,
+    /// Basin the overflow runs into, or `None` if it runs off the edge of the map
+    pub spills_into: Option<usize>,
+    pub volume: u64,
+}
+
 struct Map {
-    heights: HashMap<(i32, i32), u32>,
+    /// Heights in row-major order
+    heights: Vec<u32>,
     width: usize,
     height: usize,
 }
 
+/// A streamed row that is not as wide as the first row
+#[derive(Debug)]
+struct RaggedRowError {
+    row: usize,
+    width: usize,
+    expected: usize,
+}
+
+impl Display for RaggedRowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Row {} is {} wide but the first row is {} wide",
+            self.row, self.width, self.expected
+        )
+    }
+}
+
+impl Error for RaggedRowError {}
+
+/// Parses a stream of rows, checking each is as wide as the first
+fn parse_rows(
+    rows: impl Iterator<Item = String>,
+) -> impl Iterator<Item = Result<Vec<u32>, RaggedRowError>> {
+    let mut expected = None;
+    rows.enumerate().map(move |(row, line)| {
+        let heights = parse_row(&line);
+        let expected = *expected.get_or_insert(heights.len());
+        if heights.len() == expected {
+            Ok(heights)
+        } else {
+            Err(RaggedRowError {
+                row,
+                width: heights.len(),
+                expected,
+            })
+        }
+    })
+}
+
+fn parse_row(line: &str) -> Vec<u32> {
+    line.chars()
+        .map(|value| value.to_digit(10).expect("Failed to parse value"))
+        .collect()
+}
+
 impl From<Vec<String>> for Map {
     fn from(input: Vec<String>) -> Self {
-        let mut heights = HashMap::new();
+        let mut heights = Vec::new();
         let mut height = 0;
-        let mut width = 0;
-        for (y, line) in input.iter().enumerate() {
-            for (x, value) in line.chars().enumerate() {
-                heights.insert(
-                    (x as i32, y as i32),
-                    value.to_digit(10).expect("Failed to parse value"),
-                );
-            }
-
+        let width = input.first().map_or(0, |line| line.len());
+        for line in input.iter() {
+            let mut row = parse_row(line);
+            assert_eq!(row.len(), width, "All rows must be the same width");
+            heights.append(&mut row);
             height += 1;
-            width = line.len();
         }
 
         Self {
@@ -42,17 +196,22 @@ impl Index<(i32, i32)> for Map {
     type Output = u32;
 
     fn index(&self, index: (i32, i32)) -> &Self::Output {
-        &self.heights[&index]
+        assert!(self.contains(index), "Position {:?} is off the map", index);
+        &self.heights[self.cell_index(index)]
     }
 }
 
 impl Map {
     pub fn contains(&self, pos: (i32, i32)) -> bool {
-        self.heights.contains_key(&pos)
+        pos.0 >= 0 && pos.1 >= 0 && (pos.0 as usize) < self.width && (pos.1 as usize) < self.height
     }
 
     pub fn get(&self, pos: (i32, i32)) -> Option<u32> {
-        self.heights.get(&pos).copied()
+        if self.contains(pos) {
+            Some(self.heights[self.cell_index(pos)])
+        } else {
+            None
+        }
     }
 
     fn adjacent(&self, pos: (i32, i32)) -> Vec<(i32, i32)> {
@@ -66,68 +225,466 @@ impl Map {
 
         adjacent
     }
-}
 
-fn part_1(map: &Map) -> u32 {
-    let mut result = 0;
+    fn cell_index(&self, pos: Position) -> usize {
+        pos.1 as usize * self.width + pos.0 as usize
+    }
+
+    /// Labels every basin with union-find, joining each non-wall cell to its right and lower
+    /// neighbours, so the whole map is processed in a single pass without a search stack.
+    pub fn basins(&self) -> BasinAnalysis {
+        let mut sets = UnionFind::new(self.width * self.height);
+        for y in 0..self.height as i32 {
+            for x in 0..self.width as i32 {
+                if !matches!(self.get((x, y)), Some(height) if height < MAX_HEIGHT) {
+                    continue;
+                }
+
+                for neighbour in [(x + 1, y), (x, y + 1)] {
+                    if matches!(self.get(neighbour), Some(height) if height < MAX_HEIGHT) {
+                        sets.union(self.cell_index((x, y)), self.cell_index(neighbour));
+                    }
+                }
+            }
+        }
+
+        let mut labels = vec![None; self.width * self.height];
+        let mut roots = HashMap::new();
+        let mut basins: Vec<Basin> = Vec::new();
+        for y in 0..self.height as i32 {
+            for x in 0..self.width as i32 {
+                let pos = (x, y);
+                let height = match self.get(pos) {
+                    Some(height) if height < MAX_HEIGHT => height,
+                    _ => continue,
+                };
+
+                let index = self.cell_index(pos);
+                let id = *roots.entry(sets.find(index)).or_insert_with(|| {
+                    basins.push(Basin {
+                        id: basins.len(),
+                        low_point: pos,
+                        cells: Vec::new(),
+                        size: 0,
+                        perimeter: 0,
+                        bounding_box: (pos, pos),
+                    });
+                    basins.len() - 1
+                });
+                labels[index] = Some(id);
+
+                let basin = &mut basins[id];
+                if height < self[basin.low_point] {
+                    basin.low_point = pos;
+                }
+                basin.cells.push(pos);
+                basin.size += 1;
+                let (min, max) = &mut basin.bounding_box;
+                *min = (min.0.min(x), min.1.min(y));
+                *max = (max.0.max(x), max.1.max(y));
+            }
+        }
+
+        let mut analysis = BasinAnalysis {
+            basins,
+            labels,
+            width: self.width,
+        };
+
+        for id in 0..analysis.basins.len() {
+            let perimeter = analysis.basins[id]
+                .cells
+                .iter()
+                .flat_map(|&(x, y)| [(x, y + 1), (x + 1, y), (x, y - 1), (x - 1, y)])
+                .filter(|&neighbour| analysis.label(neighbour) != Some(id))
+                .count();
+            analysis.basins[id].perimeter = perimeter;
+        }
+
+        analysis
+    }
+
+    /// Every connected plateau of equal height that has no lower neighbour.
+    fn local_minima(&self) -> Vec<Vec<Position>> {
+        let mut seen = vec![false; self.width * self.height];
+        let mut minima = Vec::new();
+        for y in 0..self.height as i32 {
+            for x in 0..self.width as i32 {
+                if seen[self.cell_index((x, y))] {
+                    continue;
+                }
+
+                let height = self[(x, y)];
+                seen[self.cell_index((x, y))] = true;
+                let mut plateau = vec![(x, y)];
+                let mut is_minimum = true;
+                let mut edge = vec![(x, y)];
+                while let Some(pos) = edge.pop() {
+                    for adj in self.adjacent(pos) {
+                        if self[adj] < height {
+                            is_minimum = false;
+                        } else if self[adj] == height && !seen[self.cell_index(adj)] {
+                            seen[self.cell_index(adj)] = true;
+                            plateau.push(adj);
+                            edge.push(adj);
+                        }
+                    }
+                }
+
+                if is_minimum {
+                    minima.push(plateau);
+                }
+            }
+        }
 
-    for y in 0..map.height {
-        for x in 0..map.width {
-            let pos = (x as i32, y as i32);
-            if let Some(height) = map.get(pos) {
-                if map.adjacent(pos).into_iter().all(|p| map[p] > height) {
-                    result += 1 + height;
+        minima
+    }
+
+    /// Floods the map from every local minimum at once, always growing the lowest frontier cell
+    /// first, so each cell joins the catchment it would drain into.
+    pub fn watershed(&self) -> Watershed {
+        let mut labels = vec![usize::MAX; self.width * self.height];
+        let mut frontier = BinaryHeap::new();
+        let mut minima = Vec::new();
+        for (id, plateau) in self.local_minima().into_iter().enumerate() {
+            minima.push(plateau[0]);
+            for pos in plateau {
+                labels[self.cell_index(pos)] = id;
+                frontier.push(Reverse((self[pos], pos)));
+            }
+        }
+
+        while let Some(Reverse((_, pos))) = frontier.pop() {
+            let id = labels[self.cell_index(pos)];
+            for adj in self.adjacent(pos) {
+                if labels[self.cell_index(adj)] == usize::MAX {
+                    labels[self.cell_index(adj)] = id;
+                    frontier.push(Reverse((self[adj], adj)));
                 }
             }
         }
+
+        Watershed { minima, labels }
     }
 
-    result
+    /// Fills every catchment with rain until it overflows. The spill point of a basin is the
+    /// lowest pass over its boundary, either into a neighbouring basin or off the map edge.
+    pub fn rain(&self, watershed: &Watershed) -> Vec<Reservoir> {
+        let mut reservoirs: Vec<Reservoir> = (0..watershed.minima.len())
+            .map(|basin| Reservoir {
+                basin,
+                spill_height: u32::MAX,
+                spills_into: None,
+                volume: 0,
+            })
+            .collect();
+
+        for y in 0..self.height as i32 {
+            for x in 0..self.width as i32 {
+                let pos = (x, y);
+                let reservoir = &mut reservoirs[watershed.labels[self.cell_index(pos)]];
+                for neighbour in [(x, y + 1), (x + 1, y), (x, y - 1), (x - 1, y)] {
+                    let (pass, into) = match self.get(neighbour) {
+                        None => (self[pos], None),
+                        Some(height) => {
+                            let other = watershed.labels[self.cell_index(neighbour)];
+                            if other == reservoir.basin {
+                                continue;
+                            }
+
+                            (self[pos].max(height), Some(other))
+                        }
+                    };
+
+                    if pass < reservoir.spill_height {
+                        reservoir.spill_height = pass;
+                        reservoir.spills_into = into;
+                    }
+                }
+            }
+        }
+
+        for y in 0..self.height as i32 {
+            for x in 0..self.width as i32 {
+                let pos = (x, y);
+                let reservoir = &mut reservoirs[watershed.labels[self.cell_index(pos)]];
+                // A basin with no boundary at all covers the whole map and never spills
+                if reservoir.spill_height != u32::MAX && self[pos] < reservoir.spill_height {
+                    reservoir.volume += (reservoir.spill_height - self[pos]) as u64;
+                }
+            }
+        }
+
+        reservoirs
+    }
 }
 
-fn part_2(map: &Map) -> u32 {
-    let mut basins = Vec::new();
-    let mut seen = HashSet::new();
+/// Computes basin sizes one `tile_size` square at a time from a stream of rows, keeping only
+/// one band of `tile_size` rows in memory. Each tile is labelled on its own, and labels that
+/// touch across a tile boundary are merged afterwards.
+fn tiled_basin_sizes(
+    rows: impl Iterator<Item = String>,
+    tile_size: usize,
+) -> Result<Vec<usize>, RaggedRowError> {
+    let mut rows = parse_rows(rows);
+    let mut basins = UnionFind::new(0);
+    let mut cell_counts: Vec<usize> = Vec::new();
+    // Labels along the bottom row of the previous band of tiles, and the right column of the
+    // previous tile in this band
+    let mut above: Vec<Option<usize>> = Vec::new();
+    let mut left: Vec<Option<usize>> = vec![None; tile_size];
 
-    for y in 0..map.height {
-        for x in 0..map.width {
-            let pos = (x as i32, y as i32);
-            if map.contains(pos) && map[pos] < MAX_HEIGHT && !seen.contains(&pos) {
-                // New basin
-                seen.insert(pos);
-                let mut size = 1;
+    for tile_y in (0..).step_by(tile_size) {
+        let band = rows
+            .by_ref()
+            .take(tile_size)
+            .collect::<Result<Vec<Vec<u32>>, _>>()?;
+        if band.is_empty() {
+            break;
+        }
 
-                // Depth first search
-                let mut edge = vec![pos];
-                while let Some(pos) = edge.pop() {
-                    let adjacent: Vec<_> = map
-                        .adjacent(pos)
-                        .into_iter()
-                        .filter(|&p| !seen.contains(&p) && map[p] < MAX_HEIGHT)
-                        .collect();
-                    for adj in adjacent {
-                        seen.insert(adj);
-                        size += 1;
-
-                        edge.push(adj);
+        let width = band[0].len();
+        above.resize(width, None);
+
+        let tile_height = band.len();
+        for tile_x in (0..width).step_by(tile_size) {
+            let tile_width = tile_size.min(width - tile_x);
+            let local_index = |x: usize, y: usize| (y - tile_y) * tile_width + (x - tile_x);
+            let is_basin = |x: usize, y: usize| band[y - tile_y][x] < MAX_HEIGHT;
+
+            let mut sets = UnionFind::new(tile_width * tile_height);
+            for y in tile_y..tile_y + tile_height {
+                for x in tile_x..tile_x + tile_width {
+                    if !is_basin(x, y) {
+                        continue;
+                    }
+
+                    if x + 1 < tile_x + tile_width && is_basin(x + 1, y) {
+                        sets.union(local_index(x, y), local_index(x + 1, y));
+                    }
+
+                    if y + 1 < tile_y + tile_height && is_basin(x, y + 1) {
+                        sets.union(local_index(x, y), local_index(x, y + 1));
+                    }
+                }
+            }
+
+            let mut global_ids = HashMap::new();
+            let mut labels = vec![None; tile_width * tile_height];
+            for y in tile_y..tile_y + tile_height {
+                for x in tile_x..tile_x + tile_width {
+                    if !is_basin(x, y) {
+                        continue;
                     }
+
+                    let id = *global_ids
+                        .entry(sets.find(local_index(x, y)))
+                        .or_insert_with(|| {
+                            cell_counts.push(0);
+                            basins.add()
+                        });
+                    cell_counts[id] += 1;
+                    labels[local_index(x, y)] = Some(id);
+                }
+            }
+
+            // Merge with the neighbouring tiles that have already been labelled
+            for y in tile_y..tile_y + tile_height {
+                if let (Some(id), Some(neighbour)) =
+                    (labels[local_index(tile_x, y)], left[y - tile_y])
+                {
+                    basins.union(id, neighbour);
                 }
+            }
 
-                basins.push(size);
+            for x in tile_x..tile_x + tile_width {
+                if let (Some(id), Some(neighbour)) = (labels[local_index(x, tile_y)], above[x]) {
+                    basins.union(id, neighbour);
+                }
+            }
+
+            for y in tile_y..tile_y + tile_height {
+                left[y - tile_y] = labels[local_index(tile_x + tile_width - 1, y)];
+            }
+
+            for x in tile_x..tile_x + tile_width {
+                above[x] = labels[local_index(x, tile_y + tile_height - 1)];
+            }
+        }
+
+        left.iter_mut().for_each(|label| *label = None);
+    }
+
+    let mut sizes = HashMap::new();
+    for (id, count) in cell_counts.into_iter().enumerate() {
+        *sizes.entry(basins.find(id)).or_default() += count;
+    }
+
+    Ok(sizes.into_values().collect())
+}
+
+/// Finds low points from a stream of rows, keeping only the previous, current and next row in
+/// memory at any time.
+fn stream_low_points(
+    rows: impl Iterator<Item = String>,
+) -> Result<Vec<(Position, u32)>, RaggedRowError> {
+    let mut low_points = Vec::new();
+    let mut rows = parse_rows(rows).peekable();
+    let mut previous: Option<Vec<u32>> = None;
+    let mut y = 0;
+    while let Some(current) = rows.next() {
+        let current = current?;
+        // A ragged next row is reported once it becomes the current row
+        let next = rows.peek().and_then(|next| next.as_ref().ok());
+        for (x, &height) in current.iter().enumerate() {
+            let is_low_point = [
+                previous.as_ref().map(|row| row[x]),
+                next.map(|row| row[x]),
+                x.checked_sub(1).map(|x| current[x]),
+                current.get(x + 1).copied(),
+            ]
+            .into_iter()
+            .flatten()
+            .all(|adjacent| adjacent > height);
+            if is_low_point {
+                low_points.push(((x as i32, y), height));
             }
         }
+
+        previous = Some(current);
+        y += 1;
     }
 
-    basins.sort_unstable();
+    Ok(low_points)
+}
+
+fn part_1(low_points: &[(Position, u32)]) -> u32 {
+    low_points.iter().map(|(_, height)| 1 + height).sum()
+}
 
+fn part_2(analysis: &BasinAnalysis) -> usize {
     // Multiply three largest basins
-    basins[basins.len() - 3..].iter().product()
+    analysis
+        .largest(3)
+        .into_iter()
+        .map(|basin| basin.size)
+        .product()
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
+    let low_points = stream_low_points(input_parser::stream("puzzle9"))?;
+    println!("Part 1: {}", part_1(&low_points));
+
     let inputs: Vec<String> = input_parser::parse("puzzle9");
     let map = Map::from(inputs);
-    println!("Part 1: {}", part_1(&map));
-    println!("Part 2: {}", part_2(&map));
+    let analysis = map.basins();
+    println!("Part 2: {}", part_2(&analysis));
+
+    if let Some(largest) = analysis.largest(1).first() {
+        println!(
+            "{} basins; largest is #{} with low point {:?}, size {}, perimeter {}, bounds {:?}",
+            analysis.basins.len(),
+            largest.id,
+            largest.low_point,
+            largest.size,
+            largest.perimeter,
+            largest.bounding_box,
+        );
+    }
+
+    let mut tiled_sizes = tiled_basin_sizes(input_parser::stream("puzzle9"), 16)?;
+    tiled_sizes.sort_unstable();
+    println!(
+        "Part 2 (16x16 tiles): {}",
+        tiled_sizes.iter().rev().take(3).product::<usize>()
+    );
+
+    let watershed = map.watershed();
+    let reservoirs = map.rain(&watershed);
+    let deepest = reservoirs
+        .iter()
+        .max_by_key(|reservoir| reservoir.volume)
+        .expect("Map has no catchments");
+    println!(
+        "{} catchments holding {} units of rain; basin at {:?} holds the most ({}) and spills at height {} into {}",
+        reservoirs.len(),
+        reservoirs.iter().map(|reservoir| reservoir.volume).sum::<u64>(),
+        watershed.minima[deepest.basin],
+        deepest.volume,
+        deepest.spill_height,
+        match deepest.spills_into {
+            Some(basin) => format!("the basin at {:?}", watershed.minima[basin]),
+            None => String::from("the map edge"),
+        },
+    );
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: [&str; 5] = [
+        "2199943210",
+        "3987894921",
+        "9856789892",
+        "8767896789",
+        "9899965678",
+    ];
+
+    fn example() -> impl Iterator<Item = String> {
+        EXAMPLE.iter().map(|line| line.to_string())
+    }
+
+    fn sorted_sizes(analysis: &BasinAnalysis) -> Vec<usize> {
+        let mut sizes: Vec<usize> = analysis.basins.iter().map(|basin| basin.size).collect();
+        sizes.sort_unstable();
+        sizes
+    }
+
+    #[test]
+    fn streamed_low_points_are_the_basin_low_points() {
+        let analysis = Map::from(example().collect::<Vec<String>>()).basins();
+        let mut expected: Vec<Position> = analysis
+            .basins
+            .iter()
+            .map(|basin| basin.low_point)
+            .collect();
+        expected.sort_unstable();
+
+        let low_points = stream_low_points(example()).expect("Example is rectangular");
+        let mut positions: Vec<Position> = low_points.iter().map(|&(pos, _)| pos).collect();
+        positions.sort_unstable();
+        assert_eq!(positions, expected);
+        assert_eq!(part_1(&low_points), 15);
+        assert_eq!(part_2(&analysis), 1134);
+    }
+
+    #[test]
+    fn tiled_basin_sizes_match_whole_map_labelling() {
+        for rows in [example().collect(), input_parser::parse("puzzle9")] {
+            let expected = sorted_sizes(&Map::from(rows.clone()).basins());
+            for tile_size in [1, 2, 3, 4, 7, 16, 100] {
+                let mut sizes = tiled_basin_sizes(rows.iter().cloned(), tile_size)
+                    .expect("Rows are rectangular");
+                sizes.sort_unstable();
+                assert_eq!(sizes, expected, "tile size {}", tile_size);
+            }
+        }
+    }
+
+    #[test]
+    fn ragged_rows_are_reported() {
+        for ragged in [["2199", "39", "9856"], ["2199", "3987", "98"]] {
+            let rows = || ragged.iter().map(|line| line.to_string());
+            let row = ragged.iter().position(|line| line.len() != 4);
+            assert_eq!(stream_low_points(rows()).err().map(|e| e.row), row);
+            for tile_size in [1, 2, 3] {
+                assert_eq!(
+                    tiled_basin_sizes(rows(), tile_size).err().map(|e| e.row),
+                    row
+                );
+            }
+        }
+    }
+}
@@ -4,10 +4,17 @@ use std::{
 };
 
 pub fn parse(puzzle_num: &str) -> Vec<String> {
+    stream(puzzle_num).collect()
+}
+
+pub fn open(puzzle_num: &str) -> File {
     let file_path = format!("./inputs/{}", puzzle_num);
-    let file = File::open(file_path).unwrap();
-    BufReader::new(file)
+    File::open(file_path).unwrap()
+}
+
+/// Reads the input one line at a time, for inputs too large to hold in memory.
+pub fn stream(puzzle_num: &str) -> impl Iterator<Item = String> {
+    BufReader::new(open(puzzle_num))
         .lines()
         .map(|line| line.unwrap())
-        .collect()
 }
@@ -1 +1,2 @@
+pub mod big_uint;
 pub mod input_parser;